@@ -0,0 +1,129 @@
+//! Derive macros for [axum-kit](https://docs.rs/axum-kit), re-exported from
+//! `axum_kit::error` with the `macros` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, LitInt, LitStr, Result};
+
+/// Converts an error into `axum_kit::error::Error`, answered with the given
+/// status instead of a `500`.
+///
+/// - `#[status(404)]` sets the status, `500` by default.
+/// - `#[code("USER_NOT_FOUND")]` adds a stable `code` to the response.
+/// - `#[expose]` sends the `Display` message to the client. Otherwise the
+///   client only sees the reason phrase of the status.
+///
+/// On an enum, the attributes of the enum apply to every variant and those of
+/// a variant override them. The type must implement `std::error::Error`, e.g.
+/// with `thiserror`, so that its source chain can be logged.
+#[proc_macro_derive(AppError, attributes(status, code, expose))]
+pub fn derive_app_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone)]
+struct Options {
+    status: u16,
+    code: Option<LitStr>,
+    expose: bool,
+}
+
+impl Options {
+    fn parse(attrs: &[Attribute], defaults: Options) -> Result<Self> {
+        let mut options = defaults;
+        for attr in attrs {
+            if attr.path().is_ident("status") {
+                let status: LitInt = attr.parse_args()?;
+                options.status = status.base10_parse()?;
+                if !(100..=999).contains(&options.status) {
+                    return Err(Error::new_spanned(
+                        status,
+                        "status must be between 100 and 999",
+                    ));
+                }
+            } else if attr.path().is_ident("code") {
+                options.code = Some(attr.parse_args()?);
+            } else if attr.path().is_ident("expose") {
+                attr.meta.require_path_only()?;
+                options.expose = true;
+            }
+        }
+        Ok(options)
+    }
+
+    fn tokens(&self) -> TokenStream2 {
+        let Options {
+            status,
+            code,
+            expose,
+        } = self;
+        let code = match code {
+            Some(code) => quote!(::core::option::Option::Some(#code)),
+            None => quote!(::core::option::Option::None),
+        };
+        quote!((#status, #code, #expose))
+    }
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let defaults = Options::parse(
+        &input.attrs,
+        Options {
+            status: 500,
+            code: None,
+            expose: false,
+        },
+    )?;
+
+    let name = &input.ident;
+    let options = match &input.data {
+        Data::Struct(_) => defaults.tokens(),
+        Data::Enum(data) => {
+            if data.variants.is_empty() {
+                return Err(Error::new_spanned(
+                    &input,
+                    "AppError cannot be derived for an enum without variants",
+                ));
+            }
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let ident = &variant.ident;
+                    let options = Options::parse(&variant.attrs, defaults.clone())?.tokens();
+                    Ok(quote!(#name::#ident { .. } => #options,))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote!(match &error { #(#arms)* })
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input,
+                "AppError cannot be derived for a union",
+            ))
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for ::axum_kit::error::Error
+        #where_clause
+        {
+            fn from(error: #name #ty_generics) -> Self {
+                let (status, code, expose): (u16, ::core::option::Option<&'static str>, bool) =
+                    #options;
+                let status = ::axum_kit::__private::StatusCode::from_u16(status)
+                    .expect("status checked by #[derive(AppError)]");
+                let mut error = ::axum_kit::error::AppError::new(status, error).expose(expose);
+                if let ::core::option::Option::Some(code) = code {
+                    error = error.with_code(code);
+                }
+                error.into()
+            }
+        }
+    })
+}
@@ -0,0 +1,20 @@
+//! Prints the JSON Schema of the configuration, or a sample `config.toml`,
+//! for the enabled features.
+//!
+//! ```not_rust
+//! cargo run --example config_schema --features schema,postgres,redis
+//! cargo run --example config_schema --features schema,postgres,redis -- sample
+//! ```
+
+use axum_kit::config;
+
+fn main() -> anyhow::Result<()> {
+    match std::env::args().nth(1).as_deref() {
+        Some("sample") => print!("{}", config::sample_config::<()>()),
+        _ => println!(
+            "{}",
+            serde_json::to_string_pretty(&config::json_schema::<()>())?
+        ),
+    }
+    Ok(())
+}
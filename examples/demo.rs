@@ -88,10 +88,13 @@ mod route {
         routing::{get, post},
         Router,
     };
-    use axum_kit::middleware::{compression, cors, request_id, trace, trace_body};
+    use axum_kit::{
+        context::AppContext,
+        middleware::{compression, cors, request_id, trace, trace_body},
+    };
     use tower::ServiceBuilder;
 
-    pub fn init() -> Router {
+    pub fn init() -> Router<AppContext> {
         Router::new()
             .route("/", get(handler::root))
             .route("/users", post(handler::create_user))
@@ -111,15 +114,22 @@ use anyhow::Result;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let _worker_guard = axum_kit::bootstrap::Application::default("config.toml")?
+    axum_kit::bootstrap::Application::default("config.toml")?
         .with_router(route::init)
-        .before_run(|| {
-            tokio::spawn(async move {
-                println!("Running pre-run initialization tasks...");
-                Ok(())
-            })
+        .on_init(|_context| async move {
+            tracing::info!("Running initialization tasks...");
+            Ok(())
+        })
+        .after_listen(|addrs| async move {
+            for addr in addrs {
+                tracing::info!("Accepting connections on {}", addr);
+            }
+            Ok(())
+        })
+        .before_shutdown(|| async move {
+            tracing::info!("Shutting down...");
+            Ok(())
         })
         .run()
-        .await?;
-    Ok(())
+        .await
 }
@@ -1,47 +1,247 @@
 #[cfg(feature = "postgres")]
 use crate::postgres;
 
+#[cfg(feature = "postgres")]
+use sqlx::PgPool;
+
 #[cfg(feature = "redis")]
 use crate::redis;
 
+#[cfg(feature = "cli")]
+use crate::cli::{self, Cli, Command};
+
+#[cfg(feature = "i18n")]
+use crate::{i18n::Catalog, middleware::localize};
+
 use crate::{
-    config::{load_config, Config},
-    general, logger,
+    admin,
+    config::{Config, ConfigLoader, FileFormat},
+    context::AppContext,
+    general::{self, BoundListener, ListenAddr},
+    logger,
+    middleware::{cors, fallback, problem},
+    reload::{self, ConfigSource},
+    shutdown::{self, Shutdown},
 };
 use anyhow::{Context, Result};
-use axum::Router;
+use arc_swap::ArcSwap;
+use axum::{http::Extensions, Extension, Router};
+use futures_util::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::TcpListener,
+    task::{JoinHandle, JoinSet},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 use tracing_appender::non_blocking::WorkerGuard;
 
-type TaskHandle = tokio::task::JoinHandle<Result<()>>;
+type TaskHandle = JoinHandle<Result<()>>;
+type BackgroundTask = Box<dyn FnOnce(Shutdown) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+type Hook<T> = Box<dyn FnOnce(T) -> BoxFuture<'static, Result<()>> + Send + Sync>;
 
-pub struct Application {
-    config: Config,
-    router_fn: Option<Box<dyn FnOnce() -> Router + Send + Sync>>,
+fn hook<T, F, Fut>(callback: F) -> Hook<T>
+where
+    F: FnOnce(T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    Box::new(move |arg| Box::pin(callback(arg)))
+}
+
+/// Lifecycle hooks, run in registration order within each stage.
+///
+/// Stages run as follows: `on_init` once the pools and the logger are ready,
+/// `after_listen` once every listener is bound, `before_shutdown` when a shutdown
+/// is triggered but before in-flight requests are drained, and `after_shutdown`
+/// once the server and background tasks have stopped but before the pools are
+/// closed. A failing `on_init` or `after_listen` hook aborts startup; failures in
+/// the shutdown stages are logged and the remaining hooks still run. Each
+/// shutdown hook is given up to `general.shutdown_timeout` to complete.
+struct Hooks<Ext> {
+    on_init: Vec<Hook<AppContext<Ext>>>,
+    after_listen: Vec<Hook<Vec<ListenAddr>>>,
+    before_shutdown: Vec<Hook<()>>,
+    after_shutdown: Vec<Hook<()>>,
+}
+
+async fn run_hooks<T: Clone>(stage: &str, hooks: Vec<Hook<T>>, arg: T) -> Result<()> {
+    for (index, hook) in hooks.into_iter().enumerate() {
+        hook(arg.clone())
+            .await
+            .with_context(|| format!("{} hook #{} failed", stage, index + 1))?;
+    }
+    Ok(())
+}
+
+async fn run_shutdown_hooks(stage: &str, hooks: Vec<Hook<()>>, timeout: Duration) {
+    for (index, hook) in hooks.into_iter().enumerate() {
+        match tokio::time::timeout(timeout, hook(())).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => tracing::error!("{} hook #{} failed: {:?}", stage, index + 1, err),
+            Err(_) => tracing::error!(
+                "{} hook #{} did not complete within {:?}",
+                stage,
+                index + 1,
+                timeout
+            ),
+        }
+    }
+}
+
+impl<Ext> Default for Hooks<Ext> {
+    fn default() -> Self {
+        Self {
+            on_init: Vec::new(),
+            after_listen: Vec::new(),
+            before_shutdown: Vec::new(),
+            after_shutdown: Vec::new(),
+        }
+    }
+}
+
+/// Builds and runs the service. `Ext` is the type of the user-defined `[app]`
+/// configuration section, available through [`AppContext::config`].
+pub struct Application<Ext = ()> {
+    config: Config<Ext>,
+    source: Option<ConfigSource<Ext>>,
+    router_fn: Option<Box<dyn FnOnce() -> Router<AppContext<Ext>> + Send + Sync>>,
     pre_run_fn: Option<Box<dyn FnOnce() -> TaskHandle + Send + Sync>>,
+    hooks: Hooks<Ext>,
+    background_tasks: Vec<BackgroundTask>,
+    extensions: Extensions,
+    shutdown: Shutdown,
+    problem_details: bool,
+    error_fallback: bool,
+    #[cfg(feature = "i18n")]
+    catalog: Option<Catalog>,
+    #[cfg(feature = "cli")]
+    command: Option<Command>,
 }
 
 impl Application {
     pub fn default(config_path: &str) -> Result<Self> {
-        let config = load_config(config_path).with_context(|| "configuration parsing failed")?;
-        Ok(Self::new(config))
+        Self::from_file(config_path)
+    }
+}
+
+impl<Ext> Application<Ext>
+where
+    Ext: Send + Sync + 'static,
+{
+    /// Loads the configuration with [`load_config`], including the `[app]`
+    /// section as `Ext`.
+    ///
+    /// [`load_config`]: crate::config::load_config
+    ///
+    /// The configuration is loaded again on `SIGHUP` and, unless
+    /// `reload.watch` is off, when one of its files changes. See
+    /// [`crate::reload`] for what can change without a restart.
+    pub fn from_file(config_path: &str) -> Result<Self>
+    where
+        Ext: DeserializeOwned,
+    {
+        Self::from_loader(ConfigLoader::new().file(config_path))
+    }
+
+    /// Loads the configuration from `content` in the given format, merged
+    /// with the `AXUM_KIT__*` environment variables.
+    pub fn from_str(content: &str, format: FileFormat) -> Result<Self>
+    where
+        Ext: DeserializeOwned,
+    {
+        Self::from_loader(ConfigLoader::new().str(content, format))
+    }
+
+    /// Loads the whole configuration from `AXUM_KIT__*` environment
+    /// variables, e.g. `AXUM_KIT__GENERAL__LISTEN=0.0.0.0:8000`.
+    pub fn from_env() -> Result<Self>
+    where
+        Ext: DeserializeOwned,
+    {
+        Self::from_loader(ConfigLoader::new())
     }
 
-    pub fn new(config: Config) -> Self {
+    /// Loads the configuration with `loader`, which is also used to reload it
+    /// like [`Application::from_file`].
+    pub fn from_loader(loader: ConfigLoader) -> Result<Self>
+    where
+        Ext: DeserializeOwned,
+    {
+        let config = loader
+            .load()
+            .with_context(|| "configuration parsing failed")?;
+        let files = loader.files();
+        let source = ConfigSource::new(move || loader.load(), files);
+        Ok(Self {
+            source: Some(source),
+            ..Self::new(config)
+        })
+    }
+
+    pub fn new(config: Config<Ext>) -> Self {
         Self {
             config,
+            source: None,
             router_fn: None,
             pre_run_fn: None,
+            hooks: Hooks::default(),
+            background_tasks: Vec::new(),
+            extensions: Extensions::new(),
+            shutdown: Shutdown::new(),
+            problem_details: false,
+            error_fallback: true,
+            #[cfg(feature = "i18n")]
+            catalog: None,
+            #[cfg(feature = "cli")]
+            command: None,
         }
     }
 
+    /// Parses the command line with [`Cli`] and loads the configuration it
+    /// names. [`Application::run`] then performs the chosen subcommand, so a
+    /// `main` built on it serves, checks or prints the configuration, or runs
+    /// the migrations. A configuration error is returned here, which makes
+    /// `check-config` exit non-zero when `main` returns it.
+    #[cfg(feature = "cli")]
+    pub fn from_args() -> Result<Self>
+    where
+        Ext: DeserializeOwned,
+    {
+        use clap::Parser;
+        Self::from_cli(Cli::parse())
+    }
+
+    /// Like [`Application::from_args`], with an already parsed command line.
+    #[cfg(feature = "cli")]
+    pub fn from_cli(cli: Cli) -> Result<Self>
+    where
+        Ext: DeserializeOwned,
+    {
+        let mut loader = ConfigLoader::new().file(cli.config);
+        if let Some(profile) = cli.profile {
+            loader = loader.profile(profile);
+        }
+        Ok(Self {
+            command: cli.command,
+            ..Self::from_loader(loader)?
+        })
+    }
+
     pub fn with_router<F>(mut self, callback: F) -> Self
     where
-        F: FnOnce() -> Router + Send + Sync + 'static,
+        F: FnOnce() -> Router<AppContext<Ext>> + Send + Sync + 'static,
     {
         self.router_fn = Some(Box::new(callback));
         self
     }
 
+    #[deprecated(note = "use `Application::on_init` instead")]
     pub fn before_run<F>(mut self, callback: F) -> Self
     where
         F: FnOnce() -> TaskHandle + Send + Sync + 'static,
@@ -50,32 +250,520 @@ impl Application {
         self
     }
 
-    pub async fn run(self) -> Result<WorkerGuard> {
+    /// Runs once the pools and the logger are ready, before the router is built.
+    pub fn on_init<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: FnOnce(AppContext<Ext>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks.on_init.push(hook(callback));
+        self
+    }
+
+    /// Runs once every listener is bound, before the first connection is
+    /// accepted. The callback receives the bound addresses in `listen` order.
+    pub fn after_listen<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: FnOnce(Vec<ListenAddr>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks.after_listen.push(hook(callback));
+        self
+    }
+
+    /// Runs when a shutdown is triggered, before in-flight requests are drained.
+    pub fn before_shutdown<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks.before_shutdown.push(hook(move |()| callback()));
+        self
+    }
+
+    /// Runs once the server has stopped, before the pools are closed.
+    pub fn after_shutdown<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks.after_shutdown.push(hook(move |()| callback()));
+        self
+    }
+
+    /// Registers a value that handlers can read through [`AppContext::extension`].
+    pub fn with_extension<T>(mut self, value: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.extensions.insert(value);
+        self
+    }
+
+    /// Spawns a task alongside the server. The task receives a [`Shutdown`]
+    /// handle and should return once it is triggered; tasks still running after
+    /// `general.shutdown_timeout` are aborted.
+    pub fn with_background_task<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: FnOnce(Shutdown) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.background_tasks
+            .push(Box::new(move |shutdown| Box::pin(callback(shutdown))));
+        self
+    }
+
+    /// Renders errors as `application/problem+json` across the whole router,
+    /// as if it were wrapped in [`problem_details`](problem::problem_details).
+    pub fn with_problem_details(mut self) -> Self {
+        self.problem_details = true;
+        self
+    }
+
+    /// Keeps the empty `404` and `405` responses of unmatched routes and
+    /// methods instead of answering them with the body of
+    /// [`Error`](enum@crate::error::Error), see
+    /// [`error_fallback`](fallback::error_fallback).
+    pub fn without_error_fallback(mut self) -> Self {
+        self.error_fallback = false;
+        self
+    }
+
+    /// Localizes error messages across the whole router, as if it were
+    /// wrapped in [`localize`](crate::middleware::localize::localize).
+    #[cfg(feature = "i18n")]
+    pub fn with_catalog(mut self, catalog: Catalog) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Returns a handle that can trigger a graceful shutdown programmatically.
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+
+    /// Serves until `SIGINT`, `SIGTERM` or [`Shutdown::trigger`], then drains
+    /// in-flight requests, stops background tasks, closes the pools and flushes
+    /// the logger before returning.
+    ///
+    /// The logger is initialized first so that startup failures reach the
+    /// configured writer.
+    ///
+    /// With the `cli` feature, an application built by
+    /// `Application::from_args` performs the chosen subcommand instead.
+    pub async fn run(self) -> Result<()> {
+        #[cfg(feature = "cli")]
+        if let Some(command) = &self.command {
+            if !matches!(command, Command::Serve) {
+                return cli::execute(command, &self.config).await;
+            }
+        }
+        self.spawn().await?.wait().await
+    }
+
+    /// Starts the application in a background task and returns once the
+    /// listeners are bound and the `after_listen` hooks have run.
+    ///
+    /// Binding to port `0` picks an ephemeral port, which makes it possible to
+    /// run several applications side by side in tests; use
+    /// [`RunningApp::local_addr`] to find out where it is listening.
+    pub async fn spawn(self) -> Result<RunningApp> {
+        let worker_guard =
+            logger::init(&self.config.logger).with_context(|| "logger initialization failed")?;
+        match self.start().await {
+            Ok(server) => {
+                let local_addrs = server.local_addrs.clone();
+                let admin_addr = match &server.admin {
+                    Some((listener, _)) => Some(listener.local_addr()?),
+                    None => None,
+                };
+                let shutdown = server.shutdown.clone();
+                Ok(RunningApp {
+                    local_addrs,
+                    admin_addr,
+                    shutdown,
+                    handle: tokio::spawn(server.serve()),
+                    worker_guard: Some(worker_guard),
+                })
+            }
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                drop(worker_guard);
+                Err(err)
+            }
+        }
+    }
+
+    async fn start(self) -> Result<Server<Ext>> {
+        let Prepared {
+            config,
+            router,
+            admin,
+            after_listen,
+            before_shutdown,
+            after_shutdown,
+            background_tasks,
+            shutdown,
+            pools,
+        } = self.prepare().await?;
+
+        let listeners = startup_phase("listen", async {
+            let listeners = general::bind(&config.general)
+                .await
+                .with_context(|| "service startup failed")?;
+            let admin = match (admin, &config.admin) {
+                (Some(router), Some(admin_config)) => Some((
+                    admin::bind(admin_config)
+                        .await
+                        .with_context(|| "admin listener startup failed")?,
+                    router,
+                )),
+                _ => None,
+            };
+            let local_addrs = listeners
+                .iter()
+                .map(BoundListener::local_addr)
+                .collect::<Result<Vec<_>>>()?;
+            run_hooks("after_listen", after_listen, local_addrs.clone()).await?;
+            Ok((listeners, local_addrs, admin))
+        })
+        .await;
+
+        match listeners {
+            Ok((listeners, local_addrs, admin)) => Ok(Server {
+                config,
+                listeners,
+                local_addrs,
+                admin,
+                router,
+                before_shutdown,
+                after_shutdown,
+                background_tasks,
+                shutdown,
+                pools,
+            }),
+            Err(err) => {
+                pools.close().await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Connects the pools, runs the `on_init` hooks and builds the router,
+    /// without binding a listener.
+    async fn prepare(self) -> Result<Prepared<Ext>> {
         #[cfg(feature = "postgres")]
-        postgres::init(&self.config.postgres)
+        let pg_pool = startup_phase("postgres", postgres::connect(&self.config.postgres))
             .await
             .with_context(|| "postgres initialization failed")?;
 
         #[cfg(feature = "redis")]
-        redis::init(&self.config.redis)
-            .await
-            .with_context(|| "redis initialization failed")?;
+        let redis_pool = match startup_phase("redis", redis::connect(&self.config.redis)).await {
+            Ok(redis_pool) => redis_pool,
+            Err(err) => {
+                #[cfg(feature = "postgres")]
+                pg_pool.close().await;
+                return Err(err.context("redis initialization failed"));
+            }
+        };
+
+        #[cfg(feature = "postgres")]
+        postgres::install(&pg_pool);
+
+        let pools = Pools {
+            #[cfg(feature = "postgres")]
+            postgres: pg_pool.clone(),
+            #[cfg(feature = "redis")]
+            redis_installed: redis::install(&redis_pool),
+        };
+
+        let current = Arc::new(ArcSwap::from_pointee(self.config));
+        let config = current.load_full();
+        let context = AppContext::new(
+            current.clone(),
+            #[cfg(feature = "postgres")]
+            pg_pool,
+            #[cfg(feature = "redis")]
+            redis_pool,
+            self.extensions,
+        );
 
-        if let Some(callback) = self.pre_run_fn {
-            let _ = callback().await?;
+        let admin = config
+            .admin
+            .as_ref()
+            .map(|_| admin::router(context.clone(), self.shutdown.clone()));
+        let allowed_origins = cors::AllowedOrigins::new(current.clone());
+
+        let Hooks {
+            on_init,
+            after_listen,
+            before_shutdown,
+            after_shutdown,
+        } = self.hooks;
+        let mut background_tasks = self.background_tasks;
+        if let Some(source) = self.source {
+            background_tasks.push(Box::new(move |shutdown| {
+                Box::pin(reload::run(source, current, shutdown))
+            }));
         }
-        let worker_guard =
-            logger::init(&self.config.logger).with_context(|| "logger initialization failed")?;
-        let router = self
-            .router_fn
-            .map(|callback| callback())
-            .unwrap_or_else(|| {
-                Router::new().route("/", axum::routing::get(|| async { "Hello, Axum-kit!" }))
-            });
-        general::serve(&self.config.general, router)
+        let pre_run_fn = self.pre_run_fn;
+        let router_fn = self.router_fn;
+        let problem_details = self.problem_details;
+        let error_fallback = self.error_fallback;
+        #[cfg(feature = "i18n")]
+        let catalog = self.catalog;
+
+        let router = async {
+            if let Some(callback) = pre_run_fn {
+                startup_phase("before_run", async { callback().await? }).await?;
+            }
+            startup_phase("on_init", run_hooks("on_init", on_init, context.clone())).await?;
+            let mut router = router_fn
+                .map(|callback| callback())
+                .unwrap_or_else(|| {
+                    Router::new().route("/", axum::routing::get(|| async { "Hello, Axum-kit!" }))
+                })
+                .with_state(context);
+            if error_fallback {
+                router = fallback::error_fallback(router);
+            }
+            #[cfg(feature = "i18n")]
+            if let Some(catalog) = catalog {
+                router = router.layer(localize::localize(catalog));
+            }
+            if problem_details {
+                router = router.layer(problem::problem_details());
+            }
+            // Outermost, so that `cors::cors()` finds it wherever it is added.
+            Ok(router.layer(Extension(allowed_origins)))
+        }
+        .await;
+
+        match router {
+            Ok(router) => Ok(Prepared {
+                config,
+                router,
+                admin,
+                after_listen,
+                before_shutdown,
+                after_shutdown,
+                background_tasks,
+                shutdown: self.shutdown,
+                pools,
+            }),
+            Err(err) => {
+                pools.close().await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Builds the router the way [`Application::spawn`] would, without binding
+    /// a listener or installing the logger.
+    #[cfg(feature = "testing")]
+    pub(crate) async fn into_router(self) -> Result<Router> {
+        Ok(self.prepare().await?.router)
+    }
+}
+
+/// An application whose pools and router are ready but not yet bound.
+struct Prepared<Ext> {
+    config: Arc<Config<Ext>>,
+    router: Router,
+    admin: Option<Router>,
+    after_listen: Vec<Hook<Vec<ListenAddr>>>,
+    before_shutdown: Vec<Hook<()>>,
+    after_shutdown: Vec<Hook<()>>,
+    background_tasks: Vec<BackgroundTask>,
+    shutdown: Shutdown,
+    pools: Pools,
+}
+
+/// Handle to an application started with [`Application::spawn`].
+///
+/// Dropping the handle leaves the server running in the background; call
+/// [`RunningApp::shutdown`] or [`RunningApp::wait`] to stop it cleanly and flush
+/// the logger.
+pub struct RunningApp {
+    local_addrs: Vec<ListenAddr>,
+    admin_addr: Option<SocketAddr>,
+    shutdown: Shutdown,
+    handle: JoinHandle<Result<()>>,
+    worker_guard: Option<WorkerGuard>,
+}
+
+impl RunningApp {
+    /// Returns the address of the first TCP listener.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addrs.iter().find_map(|addr| match addr {
+            ListenAddr::Tcp(addr) => Some(*addr),
+            _ => None,
+        })
+    }
+
+    /// Returns the addresses of every listener, in `listen` order.
+    pub fn local_addrs(&self) -> &[ListenAddr] {
+        &self.local_addrs
+    }
+
+    /// Returns the address of the `[admin]` listener, if configured.
+    pub fn admin_addr(&self) -> Option<SocketAddr> {
+        self.admin_addr
+    }
+
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+
+    /// Takes ownership of the logger's [`WorkerGuard`], so that buffered log
+    /// lines are flushed when the caller drops it rather than when the
+    /// application stops.
+    pub fn take_worker_guard(&mut self) -> Option<WorkerGuard> {
+        self.worker_guard.take()
+    }
+
+    /// Triggers a graceful shutdown and waits for it to complete.
+    pub async fn shutdown(self) -> Result<()> {
+        self.shutdown.trigger();
+        self.wait().await
+    }
+
+    /// Waits until the application stops, e.g. after `SIGTERM`.
+    pub async fn wait(self) -> Result<()> {
+        let result = match self.handle.await {
+            Ok(result) => result,
+            Err(err) => Err(anyhow::Error::new(err).context("service task failed")),
+        };
+        match &result {
+            Ok(()) => tracing::info!("shutdown complete"),
+            Err(err) => tracing::error!("{:?}", err),
+        }
+        drop(self.worker_guard);
+        result
+    }
+}
+
+/// Pools owned by one application, closed in order on shutdown.
+struct Pools {
+    #[cfg(feature = "postgres")]
+    postgres: PgPool,
+    #[cfg(feature = "redis")]
+    redis_installed: bool,
+}
+
+impl Pools {
+    async fn close(self) {
+        // The global accessor shares the pool it was installed with, so
+        // closing it here covers `postgres::conn()` as well.
+        #[cfg(feature = "postgres")]
+        self.postgres.close().await;
+
+        #[cfg(feature = "redis")]
+        if self.redis_installed {
+            redis::close();
+        }
+    }
+}
+
+/// A started application whose listeners are bound but not yet serving.
+struct Server<Ext> {
+    config: Arc<Config<Ext>>,
+    listeners: Vec<BoundListener>,
+    local_addrs: Vec<ListenAddr>,
+    admin: Option<(TcpListener, Router)>,
+    router: Router,
+    before_shutdown: Vec<Hook<()>>,
+    after_shutdown: Vec<Hook<()>>,
+    background_tasks: Vec<BackgroundTask>,
+    shutdown: Shutdown,
+    pools: Pools,
+}
+
+impl<Ext> Server<Ext> {
+    async fn serve(self) -> Result<()> {
+        let shutdown = self.shutdown;
+        let mut tasks = JoinSet::new();
+        for task in self.background_tasks {
+            tasks.spawn(task(shutdown.clone()));
+        }
+
+        // The admin listener outlives the main ones, so `/readyz` keeps
+        // reporting the shutdown while connections drain.
+        let admin_stop = CancellationToken::new();
+        let admin = self.admin.map(|(listener, router)| {
+            tokio::spawn(admin::serve(
+                listener,
+                router,
+                admin_stop.clone().cancelled_owned(),
+            ))
+        });
+
+        let hook_timeout = self.config.general.shutdown_timeout;
+        let signal = {
+            let shutdown = shutdown.clone();
+            let before_shutdown = self.before_shutdown;
+            let shutdown_delay = self
+                .config
+                .admin
+                .as_ref()
+                .map_or(Duration::ZERO, |admin| admin.shutdown_delay);
+            async move {
+                shutdown::signal(shutdown).await;
+                if !shutdown_delay.is_zero() {
+                    tracing::info!("waiting {:?} before draining connections", shutdown_delay);
+                    tokio::time::sleep(shutdown_delay).await;
+                }
+                run_shutdown_hooks("before_shutdown", before_shutdown, hook_timeout).await;
+            }
+        };
+        let result = general::serve(&self.config.general, self.listeners, self.router, signal)
             .await
-            .with_context(|| "service startup failed")?;
+            .with_context(|| "service failed");
+        shutdown.trigger();
+
+        admin_stop.cancel();
+        if let Some(admin) = admin {
+            match admin.await {
+                Ok(Err(err)) => tracing::error!("admin listener failed: {:?}", err),
+                Err(err) => tracing::error!("admin listener task failed: {}", err),
+                Ok(Ok(())) => {}
+            }
+        }
+
+        let drain_timeout = self.config.general.shutdown_timeout;
+        let joined = tokio::time::timeout(drain_timeout, async {
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok(Err(err)) => tracing::error!("background task failed: {:?}", err),
+                    Err(err) if err.is_panic() => tracing::error!("background task panicked"),
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        if joined.is_err() {
+            tracing::warn!("background tasks did not stop in time, aborting");
+            tasks.shutdown().await;
+        }
 
-        Ok(worker_guard)
+        run_shutdown_hooks("after_shutdown", self.after_shutdown, hook_timeout).await;
+        self.pools.close().await;
+        result
     }
 }
+
+/// Runs one startup step inside a `startup` span and logs how long it took.
+async fn startup_phase<T, Fut>(phase: &'static str, step: Fut) -> Result<T>
+where
+    Fut: Future<Output = Result<T>>,
+{
+    let span = tracing::info_span!("startup", phase);
+    let started = Instant::now();
+    let result = step.instrument(span.clone()).await;
+    let elapsed = started.elapsed();
+    span.in_scope(|| match &result {
+        Ok(_) => tracing::info!(?elapsed, "completed"),
+        Err(_) => tracing::error!(?elapsed, "failed"),
+    });
+    result
+}
@@ -0,0 +1,60 @@
+use crate::{admin, config::Config};
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+/// Command line accepted by [`Application::from_args`].
+///
+/// [`Application::from_args`]: crate::bootstrap::Application::from_args
+#[derive(Debug, Parser)]
+#[command(about = None, long_about = None)]
+pub struct Cli {
+    /// Base configuration file, merged with its profile and `.local` overlays.
+    #[arg(short, long, global = true, default_value = "config.toml")]
+    pub config: String,
+    /// Profile overlay to merge, e.g. `prod` for `config.prod.toml`.
+    /// Defaults to `APP_ENV`.
+    #[arg(short, long, global = true)]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serves the application; the default without a subcommand.
+    Serve,
+    /// Loads and validates the configuration, then exits.
+    CheckConfig,
+    /// Prints the effective configuration as JSON, with secrets redacted.
+    PrintConfig,
+    /// Applies the Postgres migrations, then exits.
+    #[cfg(feature = "postgres")]
+    Migrate {
+        /// Directory holding the migration files.
+        #[arg(long, default_value = "./migrations")]
+        source: String,
+    },
+}
+
+/// Runs a command other than [`Command::Serve`].
+pub(crate) async fn execute<Ext>(command: &Command, config: &Config<Ext>) -> Result<()> {
+    match command {
+        // Served by `Application::run` itself.
+        Command::Serve => {}
+        Command::CheckConfig => println!("configuration is valid"),
+        Command::PrintConfig => {
+            let mut value = serde_json::to_value(config)?;
+            admin::redact(&mut value);
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+        #[cfg(feature = "postgres")]
+        Command::Migrate { source } => {
+            let pool = crate::postgres::connect(&config.postgres).await?;
+            let result = crate::postgres::migrate(&pool, std::path::Path::new(source)).await;
+            pool.close().await;
+            result?;
+            println!("migrations from {} applied", source);
+        }
+    }
+    Ok(())
+}
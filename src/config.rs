@@ -1,6 +1,21 @@
-use crate::{general::GeneralConfig, logger::LoggerConfig};
-use anyhow::Result;
-use serde::Deserialize;
+use crate::{
+    admin::AdminConfig, general::GeneralConfig, logger::LoggerConfig, middleware::cors::CorsConfig,
+    reload::ReloadConfig,
+};
+use ::config::{ConfigError, Environment, File, Map, Source, Value, ValueKind};
+
+pub use ::config::FileFormat;
+use anyhow::{anyhow, Context, Result};
+use serde::{
+    de::{value::MapDeserializer, DeserializeOwned, IntoDeserializer, Visitor},
+    Deserialize, Serialize,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+use validator::ValidationErrors;
 
 #[cfg(feature = "postgres")]
 use crate::postgres::PostgresConfig;
@@ -8,7 +23,28 @@ use crate::postgres::PostgresConfig;
 #[cfg(feature = "redis")]
 use crate::redis::RedisConfig;
 
+#[cfg(feature = "schema")]
+mod schema;
+
+#[cfg(feature = "schema")]
+pub use schema::{json_schema, sample_config};
+
 macro_rules! deserialize_with_context {
+    ($name:ident, Option<$type:ty>, $context:expr) => {
+        fn $name<'de, D>(deserializer: D) -> std::result::Result<Option<$type>, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let config = <Option<$type>>::deserialize(deserializer)
+                .map_err(|e| serde::de::Error::custom(format!("{}: {}", $context, e)))?;
+            if let Some(config) = &config {
+                validator::Validate::validate(config).map_err(|e| {
+                    serde::de::Error::custom(format!("{}: {}", $context, describe(&e)))
+                })?;
+            }
+            Ok(config)
+        }
+    };
     ($name:ident, $type:ty, $context:expr) => {
         fn $name<'de, D>(deserializer: D) -> std::result::Result<$type, D::Error>
         where
@@ -16,13 +52,79 @@ macro_rules! deserialize_with_context {
         {
             let config = <$type>::deserialize(deserializer)
                 .map_err(|e| serde::de::Error::custom(format!("{}: {}", $context, e)))?;
+            validator::Validate::validate(&config)
+                .map_err(|e| serde::de::Error::custom(format!("{}: {}", $context, describe(&e))))?;
             Ok(config)
         }
     };
 }
 
+/// Renders validation errors as `field: message`, leaving out the field name
+/// for struct-level checks.
+fn describe(errors: &ValidationErrors) -> String {
+    let mut messages = Vec::new();
+    for (field, errors) in errors.field_errors() {
+        for error in errors {
+            let message = error.message.as_ref().unwrap_or(&error.code);
+            match field.as_ref() {
+                "__all__" => messages.push(message.to_string()),
+                field => messages.push(format!("{}: {}", field, message)),
+            }
+        }
+    }
+    messages.sort();
+    messages.join(", ")
+}
+
+/// Serde adapter for [`Duration`](std::time::Duration) fields, accepting
+/// human-readable strings such as `"30s"`, `"10m"` or `"1h 30m"`, as well as
+/// integers counted in seconds.
+pub mod duration {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Seconds(u64),
+        Text(String),
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Raw::deserialize(deserializer)? {
+            Raw::Seconds(seconds) => Ok(Duration::from_secs(seconds)),
+            Raw::Text(text) => humantime::parse_duration(&text).map_err(|e| {
+                serde::de::Error::custom(format!("invalid duration {:?}: {}", text, e))
+            }),
+        }
+    }
+
+    pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&humantime::format_duration(*duration))
+    }
+
+    #[cfg(feature = "schema")]
+    pub fn schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "anyOf": [
+                { "type": "string" },
+                { "type": "integer", "minimum": 0 },
+            ],
+        })
+    }
+}
+
 deserialize_with_context!(deserialize_general_config, GeneralConfig, "[general]");
 deserialize_with_context!(deserialize_logger_config, LoggerConfig, "[logger]");
+deserialize_with_context!(deserialize_admin_config, Option<AdminConfig>, "[admin]");
+deserialize_with_context!(deserialize_cors_config, CorsConfig, "[cors]");
+deserialize_with_context!(deserialize_reload_config, ReloadConfig, "[reload]");
 
 #[cfg(feature = "postgres")]
 deserialize_with_context!(deserialize_postgres_config, PostgresConfig, "[postgres]");
@@ -30,12 +132,93 @@ deserialize_with_context!(deserialize_postgres_config, PostgresConfig, "[postgre
 #[cfg(feature = "redis")]
 deserialize_with_context!(deserialize_redis_config, RedisConfig, "[redis]");
 
-#[derive(Debug, Deserialize)]
-pub struct Config {
+/// Deserializes the `[app]` section, ignored when `T` is `()` or a unit struct.
+/// The loader marks a missing section as nil, which is also accepted when `T`
+/// is a struct whose fields all have defaults.
+fn deserialize_app_config<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    if let Ok(unit) = T::deserialize(UnitDeserializer) {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        return Ok(unit);
+    }
+    let config = Option::<T>::deserialize(deserializer)
+        .map_err(|e| serde::de::Error::custom(format!("[app]: {}", e)))?;
+    match config {
+        Some(config) => Ok(config),
+        None => {
+            missing_app_config().ok_or_else(|| serde::de::Error::custom("[app]: missing section"))
+        }
+    }
+}
+
+/// Returns the value of an omitted `[app]` section, if `T` allows one.
+fn missing_app_config<T: DeserializeOwned>() -> Option<T> {
+    T::deserialize(().into_deserializer())
+        .or_else(|_: serde::de::value::Error| {
+            T::deserialize(MapDeserializer::<_, serde::de::value::Error>::new(
+                std::iter::empty::<((), ())>(),
+            ))
+        })
+        .ok()
+}
+
+/// Deserializes `()` and unit structs only, unlike `().into_deserializer()`
+/// which also yields `None` for an `Option`.
+struct UnitDeserializer;
+
+impl<'de> serde::Deserializer<'de> for UnitDeserializer {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> std::result::Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("expected a unit type"))
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option newtype_struct seq tuple tuple_struct map struct
+        enum identifier ignored_any
+    }
+}
+
+/// Application configuration. `Ext` holds the user-defined `[app]` section.
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(bound(deserialize = "Ext: DeserializeOwned"))]
+pub struct Config<Ext = ()> {
     #[serde(deserialize_with = "deserialize_general_config")]
     pub general: GeneralConfig,
-    #[serde(deserialize_with = "deserialize_logger_config")]
+    #[serde(default, deserialize_with = "deserialize_logger_config")]
     pub logger: LoggerConfig,
+    /// Optional second listener serving `/healthz`, `/readyz`, `/config` and
+    /// `/loglevel`.
+    #[serde(default, deserialize_with = "deserialize_admin_config")]
+    pub admin: Option<AdminConfig>,
+    /// Origins allowed by `middleware::cors::cors()`.
+    #[serde(default, deserialize_with = "deserialize_cors_config")]
+    pub cors: CorsConfig,
+    /// Reloads applications built from a file or a `ConfigLoader` on `SIGHUP`
+    /// and file changes. The log level, `[cors]` and `[app]` apply live; other
+    /// changes are rejected until a restart.
+    #[serde(default, deserialize_with = "deserialize_reload_config")]
+    pub reload: ReloadConfig,
 
     #[cfg(feature = "postgres")]
     #[serde(deserialize_with = "deserialize_postgres_config")]
@@ -44,12 +227,467 @@ pub struct Config {
     #[cfg(feature = "redis")]
     #[serde(deserialize_with = "deserialize_redis_config")]
     pub redis: RedisConfig,
+
+    /// Left out of the serialized form, e.g. the admin `/config` endpoint.
+    #[serde(skip_serializing, deserialize_with = "deserialize_app_config")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(description = "Application settings, deserialized into `Ext`.")
+    )]
+    pub app: Ext,
+}
+
+/// Prefix of environment variables overriding the configuration, e.g.
+/// `AXUM_KIT__POSTGRES__URL` for `postgres.url`.
+pub const ENV_PREFIX: &str = "AXUM_KIT";
+
+/// Environment variable naming the profile merged over the base file.
+pub const PROFILE_ENV: &str = "APP_ENV";
+
+/// Loads the configuration from the following sources, later ones taking
+/// precedence:
+///
+/// 1. the base file `name`, e.g. `config.toml`;
+/// 2. `config.{APP_ENV}.toml` when `APP_ENV` is set, e.g. `config.prod.toml`;
+/// 3. `config.local.toml`, meant to stay out of version control;
+/// 4. `AXUM_KIT__*` environment variables, with `__` separating nested keys.
+///
+/// Only the base file is required. Once merged, `${VAR}` and `${VAR:-default}`
+/// placeholders are expanded in every string, and `file:/path` values are
+/// replaced with the contents of the file, e.g. a mounted secret.
+pub fn load_config<Ext: DeserializeOwned>(name: &str) -> Result<Config<Ext>> {
+    ConfigLoader::new().file(name).load()
 }
 
-pub fn load_config(name: &str) -> Result<Config> {
-    let config = ::config::Config::builder()
-        .add_source(config::File::with_name(name))
-        .build()?
-        .try_deserialize()?;
-    Ok(config)
+/// Returns the source that supplied each value of the layered configuration,
+/// keyed by dotted path (e.g. `postgres.url` => `the environment`).
+pub fn config_origins(name: &str) -> Result<BTreeMap<String, String>> {
+    ConfigLoader::new().file(name).origins()
+}
+
+/// Combines configuration sources, later ones taking precedence, followed by
+/// the `AXUM_KIT__*` environment variables unless disabled with
+/// [`ConfigLoader::env`].
+///
+/// A loader without any file reads the whole configuration from the
+/// environment, e.g. `AXUM_KIT__GENERAL__LISTEN=0.0.0.0:8000`.
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    layers: Vec<Layer>,
+    profile: Option<String>,
+    env: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Layer {
+    File { name: String, required: bool },
+    Str { content: String, format: FileFormat },
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            profile: None,
+            env: true,
+        }
+    }
+
+    /// Adds the file `name` with its `APP_ENV` profile and `.local` overlays,
+    /// as described in [`load_config`]. The extension may be left out to
+    /// pick up any supported format.
+    pub fn file(mut self, name: impl Into<String>) -> Self {
+        self.layers.push(Layer::File {
+            name: name.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Like [`ConfigLoader::file`], but a missing file is skipped.
+    pub fn optional_file(mut self, name: impl Into<String>) -> Self {
+        self.layers.push(Layer::File {
+            name: name.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Adds configuration held in a string, e.g. embedded with `include_str!`.
+    pub fn str(mut self, content: impl Into<String>, format: FileFormat) -> Self {
+        self.layers.push(Layer::Str {
+            content: content.into(),
+            format,
+        });
+        self
+    }
+
+    /// Selects the profile overlay of each file instead of `APP_ENV`.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Whether to merge the `AXUM_KIT__*` environment variables last, which
+    /// is the default.
+    pub fn env(mut self, enabled: bool) -> Self {
+        self.env = enabled;
+        self
+    }
+
+    pub fn load<Ext: DeserializeOwned>(&self) -> Result<Config<Ext>> {
+        let config = self.build()?.try_deserialize()?;
+        Ok(config)
+    }
+
+    /// Returns the source that supplied each value, keyed by dotted path.
+    pub fn origins(&self) -> Result<BTreeMap<String, String>> {
+        let mut origins = BTreeMap::new();
+        for (key, value) in self.build()?.collect()? {
+            collect_origins(key, value, &mut origins);
+        }
+        Ok(origins)
+    }
+
+    /// Lists the files this loader may read, whether or not they exist.
+    /// Names without an extension expand to every supported format.
+    pub(crate) fn files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for layer in &self.layers {
+            let Layer::File { name, .. } = layer else {
+                continue;
+            };
+            for name in self.file_names(name) {
+                if Path::new(&name).extension().is_some() {
+                    files.push(PathBuf::from(name));
+                } else {
+                    files.extend(
+                        ["toml", "json", "yaml", "yml", "ini", "ron", "json5"]
+                            .iter()
+                            .map(|extension| PathBuf::from(format!("{}.{}", name, extension))),
+                    );
+                }
+            }
+        }
+        files
+    }
+
+    /// Returns `name` followed by its profile and `.local` overlays.
+    fn file_names(&self, name: &str) -> Vec<String> {
+        let mut names = vec![name.to_string()];
+        let profile = self
+            .profile
+            .clone()
+            .or_else(|| std::env::var(PROFILE_ENV).ok());
+        if let Some(profile) = profile {
+            names.push(overlay(name, &profile));
+        }
+        names.push(overlay(name, "local"));
+        names
+    }
+
+    fn build(&self) -> Result<::config::Config> {
+        let mut builder = ::config::Config::builder().set_default("app", ValueKind::Nil)?;
+        for layer in &self.layers {
+            match layer {
+                Layer::File { name, required } => {
+                    for (index, name) in self.file_names(name).into_iter().enumerate() {
+                        let required = *required && index == 0;
+                        builder = builder.add_source(File::with_name(&name).required(required));
+                    }
+                }
+                Layer::Str { content, format } => {
+                    builder = builder.add_source(File::from_str(content, *format));
+                }
+            }
+        }
+        if self.env {
+            builder = builder.add_source(
+                Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("__")
+                    .separator("__")
+                    .try_parsing(true)
+                    .list_separator(",")
+                    .with_list_parse_key("general.listen"),
+            );
+        }
+        let config = builder.build()?;
+
+        let mut values = config.collect()?;
+        for (key, value) in values.iter_mut() {
+            resolve(key, value)?;
+        }
+        let config = ::config::Config::builder()
+            .add_source(Resolved(values))
+            .build()?;
+        Ok(config)
+    }
+}
+
+/// Returns `name` with `suffix` inserted before its extension, e.g.
+/// `config.prod.toml` for `config.toml`.
+fn overlay(name: &str, suffix: &str) -> String {
+    let path = Path::new(name);
+    match path.extension() {
+        Some(extension) => path
+            .with_extension(format!("{}.{}", suffix, extension.to_string_lossy()))
+            .to_string_lossy()
+            .into_owned(),
+        None => format!("{}.{}", name, suffix),
+    }
+}
+
+/// Merged values with every placeholder resolved, keeping their origins.
+#[derive(Debug, Clone)]
+struct Resolved(Map<String, Value>);
+
+impl Source for Resolved {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> std::result::Result<Map<String, Value>, ConfigError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Resolves `${VAR}` and `${VAR:-default}` placeholders in every string, then
+/// replaces `file:/path` values with the contents of the file. `$${` is kept
+/// as a literal `${`.
+fn resolve(key: &str, value: &mut Value) -> Result<()> {
+    match &mut value.kind {
+        ValueKind::String(text) => {
+            *text = resolve_string(text).with_context(|| format!("failed to resolve `{}`", key))?;
+        }
+        ValueKind::Table(table) => {
+            for (child, value) in table.iter_mut() {
+                resolve(&format!("{}.{}", key, child), value)?;
+            }
+        }
+        ValueKind::Array(values) => {
+            for (index, value) in values.iter_mut().enumerate() {
+                resolve(&format!("{}[{}]", key, index), value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn resolve_string(text: &str) -> Result<String> {
+    let text = interpolate(text)?;
+    match text.strip_prefix("file:") {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path))?;
+            Ok(contents.trim_end_matches(['\r', '\n']).to_string())
+        }
+        None => Ok(text),
+    }
+}
+
+fn interpolate(text: &str) -> Result<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            output.push_str("${");
+            rest = after;
+            continue;
+        }
+        let Some(after) = rest.strip_prefix("${") else {
+            output.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow!("unterminated placeholder in {:?}", text))?;
+        let (name, default) = match after[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&after[..end], None),
+        };
+        // Like the shell, `:-` also applies when the variable is set but empty.
+        match (std::env::var(name), default) {
+            (Ok(value), None) => output.push_str(&value),
+            (Ok(value), Some(_)) if !value.is_empty() => output.push_str(&value),
+            (_, Some(default)) => output.push_str(default),
+            (Err(_), None) => return Err(anyhow!("environment variable `{}` is not set", name)),
+        }
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Wraps a sensitive value, such as a URL carrying a password, so that it is
+/// redacted when the configuration is logged or serialized.
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl<T: schemars::JsonSchema> schemars::JsonSchema for Secret<T> {
+    fn inline_schema() -> bool {
+        T::inline_schema()
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        T::schema_name()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        T::json_schema(generator)
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([redacted])")
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str("[redacted]")
+    }
+}
+
+fn collect_origins(key: String, value: Value, origins: &mut BTreeMap<String, String>) {
+    let origin = value.origin().unwrap_or("default").to_string();
+    match value.kind {
+        ValueKind::Table(table) => {
+            for (child, value) in table {
+                collect_origins(format!("{}.{}", key, child), value, origins);
+            }
+        }
+        _ => {
+            origins.insert(key, origin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load<Ext: DeserializeOwned>(content: &str) -> Result<Config<Ext>> {
+        ConfigLoader::new()
+            .str(content, FileFormat::Toml)
+            .env(false)
+            .load()
+    }
+
+    /// The sections required with the enabled features, followed by `extra`.
+    fn fixture(extra: &str) -> String {
+        let mut content = "[general]\nlisten = \"127.0.0.1:0\"\n".to_string();
+        if cfg!(feature = "postgres") {
+            content.push_str("[postgres]\nurl = \"postgres://127.0.0.1/app\"\n");
+        }
+        if cfg!(feature = "redis") {
+            content.push_str("[redis]\nurl = \"redis://127.0.0.1\"\n");
+        }
+        content + extra
+    }
+
+    #[test]
+    fn unit_app_config_ignores_section() {
+        load::<()>(&fixture("[app]\nname = \"demo\"\n")).unwrap();
+        load::<()>(&fixture("")).unwrap();
+    }
+
+    #[test]
+    fn app_config_is_deserialized() {
+        #[derive(Debug, Deserialize)]
+        struct App {
+            name: String,
+        }
+
+        let content = fixture("[app]\nname = \"demo\"\n");
+        assert_eq!(load::<App>(&content).unwrap().app.name, "demo");
+        assert_eq!(
+            load::<Option<App>>(&content).unwrap().app.unwrap().name,
+            "demo"
+        );
+        assert!(load::<App>(&fixture("[app]\nother = 1\n")).is_err());
+    }
+
+    #[test]
+    fn logger_file_settings_checked_for_file_writer() {
+        let content = fixture("[logger]\ndirectory = \"\"\n");
+        load::<()>(&content).unwrap();
+        assert!(load::<()>(&format!("{}writer = \"file\"\n", content)).is_err());
+    }
+
+    #[test]
+    fn interpolate_variable() {
+        std::env::set_var("AXUM_KIT_TEST_HOST", "db.internal");
+        assert_eq!(
+            interpolate("postgres://${AXUM_KIT_TEST_HOST}:5432").unwrap(),
+            "postgres://db.internal:5432"
+        );
+        assert!(interpolate("${AXUM_KIT_TEST_UNSET}").is_err());
+    }
+
+    #[test]
+    fn interpolate_default() {
+        std::env::set_var("AXUM_KIT_TEST_EMPTY", "");
+        assert_eq!(interpolate("${AXUM_KIT_TEST_EMPTY:-info}").unwrap(), "info");
+        assert_eq!(
+            interpolate("${AXUM_KIT_TEST_MISSING:-info}").unwrap(),
+            "info"
+        );
+        assert_eq!(interpolate("${AXUM_KIT_TEST_MISSING:-}").unwrap(), "");
+    }
+
+    #[test]
+    fn interpolate_escape() {
+        assert_eq!(
+            interpolate("$${HOME} costs $5").unwrap(),
+            "${HOME} costs $5"
+        );
+    }
+
+    #[test]
+    fn interpolate_unterminated() {
+        assert!(interpolate("postgres://${AXUM_KIT_TEST_HOST").is_err());
+    }
+
+    #[test]
+    fn resolve_string_reads_file() {
+        let path = std::env::temp_dir().join(format!("axum-kit-test-{}", std::process::id()));
+        std::fs::write(&path, "s3cret\n").unwrap();
+        std::env::set_var("AXUM_KIT_TEST_SECRET_FILE", &path);
+        let resolved = resolve_string("file:${AXUM_KIT_TEST_SECRET_FILE}");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resolved.unwrap(), "s3cret");
+        assert!(resolve_string("file:/nonexistent/axum-kit").is_err());
+    }
 }
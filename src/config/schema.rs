@@ -0,0 +1,183 @@
+use super::{missing_app_config, Config};
+use schemars::{JsonSchema, Schema};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// Returns the JSON Schema of `Config<Ext>`, covering the sections of the
+/// enabled cargo features. `[app]` is only required when `Ext` cannot be
+/// built from a missing section.
+pub fn json_schema<Ext: JsonSchema + DeserializeOwned>() -> Schema {
+    let mut schema = schemars::schema_for!(Config<Ext>);
+    if missing_app_config::<Ext>().is_some() {
+        if let Some(Value::Array(required)) = schema.get_mut("required") {
+            required.retain(|key| key != "app");
+        }
+    }
+    schema
+}
+
+/// Renders a commented `config.toml` from [`json_schema`]. Fields are set to
+/// their defaults, required fields to an example value, and optional sections
+/// and fields without a default are commented out.
+pub fn sample_config<Ext: JsonSchema + DeserializeOwned>() -> String {
+    let schema = json_schema::<Ext>();
+    let root = schema.as_value();
+    let mut sample = Sample {
+        defs: root.get("$defs").and_then(Value::as_object),
+        output: String::new(),
+    };
+    let required = required(root);
+    for (name, property) in properties(root) {
+        sample.section(name, property, required.contains(&name.as_str()), false);
+    }
+    sample.output
+}
+
+struct Sample<'a> {
+    defs: Option<&'a Map<String, Value>>,
+    output: String,
+}
+
+impl<'a> Sample<'a> {
+    fn section(&mut self, path: &str, property: &'a Value, is_required: bool, commented: bool) {
+        let (schema, nullable) = self.resolve(property);
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            return;
+        }
+        let commented = commented || (nullable && !is_required);
+        let prefix = if commented { "# " } else { "" };
+
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.comment(description(schema).or_else(|| description(property)));
+        self.output.push_str(&format!("{}[{}]\n", prefix, path));
+
+        let required = required(schema);
+        let mut nested = Vec::new();
+        for (name, field) in properties(schema) {
+            let field_required = required.contains(&name.as_str());
+            let (resolved, _) = self.resolve(field);
+            if resolved.get("type").and_then(Value::as_str) == Some("object") {
+                nested.push((name, field, field_required));
+                continue;
+            }
+
+            self.comment(description(field));
+            if let Some(Value::Array(values)) = resolved.get("enum") {
+                let values: Vec<_> = values.iter().map(toml).collect();
+                self.comment(Some(&format!("One of {}.", values.join(", "))));
+            }
+            let example = field
+                .get("examples")
+                .and_then(Value::as_array)
+                .and_then(|examples| examples.first());
+            match (field.get("default"), example) {
+                (Some(default), _) if !default.is_null() => {
+                    self.output
+                        .push_str(&format!("{}{} = {}\n", prefix, name, toml(default)));
+                }
+                (_, Some(example)) if field_required => {
+                    self.output.push_str(&format!(
+                        "{}{} = {}    # Required.\n",
+                        prefix,
+                        name,
+                        toml(example)
+                    ));
+                }
+                (_, Some(example)) => {
+                    self.output
+                        .push_str(&format!("# {} = {}\n", name, toml(example)));
+                }
+                (_, None) if field_required => {
+                    self.output
+                        .push_str(&format!("{}{} = \"\"    # Required.\n", prefix, name));
+                }
+                _ => {}
+            }
+        }
+
+        for (name, field, field_required) in nested {
+            self.section(
+                &format!("{}.{}", path, name),
+                field,
+                field_required,
+                commented,
+            );
+        }
+    }
+
+    /// Follows a `$ref`, possibly wrapped in an `anyOf` with `null` for
+    /// `Option` fields, and reports whether `null` is accepted.
+    fn resolve(&self, schema: &'a Value) -> (&'a Value, bool) {
+        if let Some(Value::Array(variants)) = schema.get("anyOf") {
+            let null = variants
+                .iter()
+                .any(|variant| variant.get("type").and_then(Value::as_str) == Some("null"));
+            let other = variants
+                .iter()
+                .find(|variant| variant.get("type").and_then(Value::as_str) != Some("null"));
+            if let (true, Some(other)) = (null, other) {
+                return (self.resolve(other).0, true);
+            }
+        }
+        let target = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix("#/$defs/"))
+            .and_then(|name| self.defs?.get(name));
+        match target {
+            Some(target) => self.resolve(target),
+            None => (schema, false),
+        }
+    }
+
+    fn comment(&mut self, text: Option<&str>) {
+        for line in text.into_iter().flat_map(str::lines) {
+            match line.trim_end() {
+                "" => self.output.push_str("#\n"),
+                line => self.output.push_str(&format!("# {}\n", line)),
+            }
+        }
+    }
+}
+
+fn properties(schema: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+fn description(schema: &Value) -> Option<&str> {
+    schema.get("description").and_then(Value::as_str)
+}
+
+/// Formats a JSON value as an inline TOML value.
+fn toml(value: &Value) -> String {
+    match value {
+        Value::Array(values) => {
+            let values: Vec<_> = values.iter().map(toml).collect();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Object(map) => {
+            let entries: Vec<_> = map
+                .iter()
+                .map(|(key, value)| format!("{} = {}", key, toml(value)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(feature = "postgres")]
+use sqlx::PgPool;
+
+#[cfg(feature = "redis")]
+use crate::redis::RedisPool;
+
+use crate::config::Config;
+use arc_swap::ArcSwap;
+use axum::{extract::FromRef, http::Extensions};
+use std::sync::Arc;
+
+/// Shared application state handed to routers through [`Router::with_state`].
+///
+/// Cloning is cheap: the configuration and extensions are reference counted
+/// and the pools are handles to shared connection pools. `Ext` is the type of
+/// the `[app]` configuration section.
+///
+/// The configuration is swapped atomically when it is reloaded, so handlers
+/// should call [`AppContext::config`] per request rather than keep the result.
+///
+/// [`Router::with_state`]: axum::Router::with_state
+pub struct AppContext<Ext = ()> {
+    config: Arc<ArcSwap<Config<Ext>>>,
+
+    #[cfg(feature = "postgres")]
+    postgres: PgPool,
+
+    #[cfg(feature = "redis")]
+    redis: RedisPool,
+
+    extensions: Arc<Extensions>,
+}
+
+impl<Ext> AppContext<Ext> {
+    pub(crate) fn new(
+        config: Arc<ArcSwap<Config<Ext>>>,
+        #[cfg(feature = "postgres")] postgres: PgPool,
+        #[cfg(feature = "redis")] redis: RedisPool,
+        extensions: Extensions,
+    ) -> Self {
+        Self {
+            config,
+            #[cfg(feature = "postgres")]
+            postgres,
+            #[cfg(feature = "redis")]
+            redis,
+            extensions: Arc::new(extensions),
+        }
+    }
+
+    /// Returns the current configuration, including the `[app]` section.
+    pub fn config(&self) -> Arc<Config<Ext>> {
+        self.config.load_full()
+    }
+
+    #[cfg(feature = "postgres")]
+    pub fn postgres(&self) -> &PgPool {
+        &self.postgres
+    }
+
+    #[cfg(feature = "redis")]
+    pub fn redis(&self) -> &RedisPool {
+        &self.redis
+    }
+
+    /// Returns a value registered with [`Application::with_extension`].
+    ///
+    /// [`Application::with_extension`]: crate::bootstrap::Application::with_extension
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get::<T>()
+    }
+}
+
+impl<Ext> Clone for AppContext<Ext> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            #[cfg(feature = "postgres")]
+            postgres: self.postgres.clone(),
+            #[cfg(feature = "redis")]
+            redis: self.redis.clone(),
+            extensions: self.extensions.clone(),
+        }
+    }
+}
+
+impl<Ext: std::fmt::Debug> std::fmt::Debug for AppContext<Ext> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AppContext")
+            .field("config", &self.config.load())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Ext> FromRef<AppContext<Ext>> for Arc<Config<Ext>> {
+    fn from_ref(context: &AppContext<Ext>) -> Self {
+        context.config()
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl<Ext> FromRef<AppContext<Ext>> for PgPool {
+    fn from_ref(context: &AppContext<Ext>) -> Self {
+        context.postgres.clone()
+    }
+}
+
+#[cfg(feature = "redis")]
+impl<Ext> FromRef<AppContext<Ext>> for RedisPool {
+    fn from_ref(context: &AppContext<Ext>) -> Self {
+        context.redis.clone()
+    }
+}
@@ -1,6 +1,25 @@
-use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse, Json};
-use serde::Serialize;
+use axum::{
+    extract::rejection::{
+        BytesRejection, ExtensionRejection, FormRejection, JsonRejection, MatchedPathRejection,
+        NestedPathRejection, PathRejection, QueryRejection, RawFormRejection,
+        RawPathParamsRejection, StringRejection,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use thiserror::Error;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+#[cfg(feature = "postgres")]
+mod database;
+#[cfg(feature = "postgres")]
+pub use database::{map_constraint, map_sqlstate, DatabaseErrorMapping};
+
+#[cfg(feature = "macros")]
+pub use axum_kit_macros::AppError;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -23,9 +42,37 @@ pub enum Error {
     #[error(transparent)]
     JsonExtractorRejection(#[from] JsonRejection),
 
-    /// Return `422 Unprocessable Entity`
+    /// Return `400 Bad Request`
+    #[error(transparent)]
+    QueryExtractorRejection(#[from] QueryRejection),
+
+    /// Return
+    /// - `400 Bad Request`
+    /// - `500 Internal Server Error` (Missing Path Params)
     #[error(transparent)]
-    ValidationError(#[from] validator::ValidationErrors),
+    PathExtractorRejection(#[from] PathRejection),
+
+    /// Return
+    /// - `400 Bad Request`
+    /// - `415 Unsupported Media Type`
+    /// - `422 Unprocessable Entity`
+    #[error(transparent)]
+    FormExtractorRejection(#[from] FormRejection),
+
+    /// Return `400 Bad Request`
+    #[cfg(feature = "typed-header")]
+    #[error(transparent)]
+    TypedHeaderExtractorRejection(#[from] axum_extra::typed_header::TypedHeaderRejection),
+
+    /// Return the status of the rejection of another axum extractor, e.g.
+    /// `Extension` or `Bytes`
+    #[error("{1}")]
+    Rejection(StatusCode, String),
+
+    /// Return `422 Unprocessable Entity`, listing each failing field in
+    /// `errors`
+    #[error(transparent)]
+    ValidationError(#[from] ValidationErrors),
 
     /// Return `500 Internal Server Error`
     #[cfg(feature = "redis")]
@@ -34,7 +81,11 @@ pub enum Error {
 
     /// Return
     /// - `404 Not Found` (Database Record Not Found)
-    /// - `409 Conflict` (Unique Constraint Violation)
+    /// - `409 Conflict` (Unique Constraint, Foreign Key Violation,
+    ///   Serialization Failure, Deadlock Detected)
+    /// - `422 Unprocessable Entity` (Not Null, Check Constraint Violation)
+    /// - `503 Service Unavailable` with `Retry-After` (Pool Timed Out)
+    /// - the status set with [`map_sqlstate`] or [`map_constraint`]
     /// - `500 Internal Server Error`
     #[cfg(feature = "postgres")]
     #[error(transparent)]
@@ -46,48 +97,476 @@ pub enum Error {
 
     #[error("{1}")]
     Custom(StatusCode, String),
+
+    /// Return the status of the [`AppError`](struct@AppError), with its code
+    #[error(transparent)]
+    App(Box<AppError>),
+
+    /// Return the status of the [`Problem`], with its extension members in
+    /// problem details mode
+    #[error("{}", .0.message())]
+    Problem(Box<Problem>),
+}
+
+impl From<Problem> for Error {
+    fn from(problem: Problem) -> Self {
+        Self::Problem(Box::new(problem))
+    }
+}
+
+macro_rules! impl_from_rejection {
+    ($($rejection:ty),* $(,)?) => {
+        $(
+            impl From<$rejection> for Error {
+                fn from(rejection: $rejection) -> Self {
+                    Self::Rejection(rejection.status(), rejection.body_text())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_rejection!(
+    BytesRejection,
+    ExtensionRejection,
+    MatchedPathRejection,
+    NestedPathRejection,
+    RawFormRejection,
+    RawPathParamsRejection,
+    StringRejection,
+);
+
+impl From<AppError> for Error {
+    fn from(error: AppError) -> Self {
+        Self::App(Box::new(error))
+    }
+}
+
+/// A domain error answered with its own status, usually converted by
+/// `#[derive(AppError)]` with the `macros` feature.
+///
+/// The client sees the status, the code and, only when exposed, the message.
+/// Server errors are logged with their source chain.
+#[derive(Debug)]
+pub struct AppError {
+    status: StatusCode,
+    code: Option<String>,
+    expose: bool,
+    error: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl AppError {
+    pub fn new(
+        status: StatusCode,
+        error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self {
+            status,
+            code: None,
+            expose: false,
+            error: error.into(),
+        }
+    }
+
+    /// Sets the stable `code` of the response, e.g. `USER_NOT_FOUND`.
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Whether the message of the error is sent to the client instead of the
+    /// reason phrase of the status.
+    pub fn expose(mut self, expose: bool) -> Self {
+        self.expose = expose;
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    fn problem(&self) -> Problem {
+        if self.status.is_server_error() {
+            let mut chain = self.to_string();
+            let mut source = std::error::Error::source(self);
+            while let Some(error) = source {
+                chain.push_str(&format!(": {}", error));
+                source = error.source();
+            }
+            tracing::error!("{}", chain);
+        }
+        let mut problem = Problem::new(self.status);
+        if self.expose {
+            problem = problem.with_detail(self.to_string());
+        }
+        if let Some(code) = &self.code {
+            problem = problem.with_code(code);
+        }
+        problem
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+/// The wrapped error is displayed in place of this one, so the chain
+/// continues with its source.
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// JSON body returned for every [`enum@Error`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub message: String,
+    /// Failing fields of a `422 Unprocessable Entity`, left out otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// Stable code of the error, e.g. `USER_NOT_FOUND` or
+    /// `UNIQUE_VIOLATION`, or else the reason phrase of the status in upper
+    /// snake case, e.g. `NOT_FOUND`.
+    #[serde(default)]
+    pub code: String,
+    /// Violated database constraint, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraint: Option<String>,
+}
+
+/// One failed validation rule, as listed in the `errors` member of a `422`
+/// response.
+///
+/// ```json
+/// {
+///   "field": "items[0].name",
+///   "code": "length",
+///   "message": "Can not be empty",
+///   "params": { "min": 1 }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Path of the field: nested fields are joined with `.` and list items
+    /// are indexed, e.g. `address.city` or `items[0].name`. Empty for rules
+    /// on the whole body.
+    pub field: String,
+    /// Name of the rule, e.g. `length`, `range`, `email`, or the code of a
+    /// custom validator.
+    pub code: String,
+    /// Message set on the rule, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Parameters of the rule, e.g. `min` and `max`. The rejected value is
+    /// left out so that it is not echoed back.
+    pub params: Map<String, Value>,
+}
+
+/// Flattens nested [`ValidationErrors`] into one [`FieldError`] per failed
+/// rule, sorted by field path.
+pub fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut fields = Vec::new();
+    collect_field_errors("", errors, &mut fields);
+    fields.sort_by(|a, b| a.field.cmp(&b.field));
+    fields
+}
+
+/// Lists the failing fields with their messages, or rule codes, e.g.
+/// `email: email, items[0].name: Can not be empty`.
+fn summarize(errors: &[FieldError]) -> String {
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| {
+            let message = error.message.as_deref().unwrap_or(&error.code);
+            match error.field.as_str() {
+                "" => message.to_string(),
+                field => format!("{}: {}", field, message),
+            }
+        })
+        .collect();
+    errors.join(", ")
+}
+
+fn collect_field_errors(path: &str, errors: &ValidationErrors, fields: &mut Vec<FieldError>) {
+    for (name, kind) in errors.errors() {
+        let field = match (path, name.as_ref()) {
+            (path, "__all__") => path.to_string(),
+            ("", name) => name.to_string(),
+            (path, name) => format!("{}.{}", path, name),
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                fields.extend(errors.iter().map(|error| {
+                    let mut params: Vec<_> = error
+                        .params
+                        .iter()
+                        .filter(|(name, _)| *name != "value")
+                        .map(|(name, value)| (name.to_string(), value.clone()))
+                        .collect();
+                    params.sort_by(|a, b| a.0.cmp(&b.0));
+                    FieldError {
+                        field: field.clone(),
+                        code: error.code.to_string(),
+                        message: error.message.as_ref().map(|message| message.to_string()),
+                        params: params.into_iter().collect(),
+                    }
+                }));
+            }
+            ValidationErrorsKind::Struct(errors) => collect_field_errors(&field, errors, fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_field_errors(&format!("{}[{}]", field, index), errors, fields);
+                }
+            }
+        }
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        #[derive(Serialize)]
-        struct ErrorResponse {
-            message: String,
-        }
         let (status, message) = match self {
             Self::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
             Self::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
             Self::NotFound => (StatusCode::NOT_FOUND, self.to_string()),
-            Self::JsonExtractorRejection(json_rejection) => {
-                (json_rejection.status(), json_rejection.body_text())
+            Self::JsonExtractorRejection(ref rejection) => {
+                let code = match rejection {
+                    JsonRejection::JsonDataError(_) => Some("JSON_DATA_ERROR"),
+                    JsonRejection::JsonSyntaxError(_) => Some("JSON_SYNTAX_ERROR"),
+                    JsonRejection::MissingJsonContentType(_) => Some("MISSING_JSON_CONTENT_TYPE"),
+                    _ => None,
+                };
+                return rejection_response(rejection.status(), code, rejection.body_text());
+            }
+            Self::QueryExtractorRejection(ref rejection) => {
+                let code = match rejection {
+                    QueryRejection::FailedToDeserializeQueryString(_) => Some("INVALID_QUERY"),
+                    _ => None,
+                };
+                return rejection_response(rejection.status(), code, rejection.body_text());
+            }
+            Self::PathExtractorRejection(ref rejection) => {
+                let code = match rejection {
+                    PathRejection::FailedToDeserializePathParams(_) => Some("INVALID_PATH_PARAMS"),
+                    _ => None,
+                };
+                return rejection_response(rejection.status(), code, rejection.body_text());
+            }
+            Self::FormExtractorRejection(ref rejection) => {
+                let code = match rejection {
+                    FormRejection::InvalidFormContentType(_) => Some("INVALID_FORM_CONTENT_TYPE"),
+                    FormRejection::FailedToDeserializeForm(_)
+                    | FormRejection::FailedToDeserializeFormBody(_) => Some("INVALID_FORM"),
+                    _ => None,
+                };
+                return rejection_response(rejection.status(), code, rejection.body_text());
+            }
+            #[cfg(feature = "typed-header")]
+            Self::TypedHeaderExtractorRejection(ref rejection) => {
+                let code = if rejection.is_missing() {
+                    "MISSING_HEADER"
+                } else {
+                    "INVALID_HEADER"
+                };
+                return Problem::new(StatusCode::BAD_REQUEST)
+                    .with_detail(rejection.to_string())
+                    .with_code(code)
+                    .with_extension("header", rejection.name().as_str())
+                    .into_response();
+            }
+            Self::Rejection(status, ref message) => {
+                return rejection_response(status, None, message.clone());
+            }
+            Self::ValidationError(ref errors) => {
+                let errors = field_errors(errors);
+                return Problem::new(StatusCode::UNPROCESSABLE_ENTITY)
+                    .with_detail(summarize(&errors))
+                    .with_code("VALIDATION_FAILED")
+                    .with_extension("errors", errors)
+                    .into_response();
             }
-            Self::ValidationError(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
 
             #[cfg(feature = "redis")]
             Self::Redis(_) => internal_server_error(self),
 
             #[cfg(feature = "postgres")]
-            Self::Sqlx(ref error) => match error {
-                sqlx::Error::RowNotFound => (
-                    StatusCode::NOT_FOUND,
-                    "Database Record Not Found".to_string(),
-                ),
-                sqlx::Error::Database(db_error)
-                    if db_error.code().is_some_and(|code| code == "23505") =>
-                {
-                    (
-                        StatusCode::CONFLICT,
-                        "Unique Constraint Violation".to_string(),
-                    )
+            Self::Sqlx(ref error) => match database::problem(error) {
+                Some((problem, retry_after)) => {
+                    let mut response = problem.into_response();
+                    if let Some(retry_after) = retry_after {
+                        response.headers_mut().insert(
+                            axum::http::header::RETRY_AFTER,
+                            axum::http::HeaderValue::from(retry_after.as_secs().max(1)),
+                        );
+                    }
+                    return response;
                 }
-                _ => internal_server_error(self),
+                None => internal_server_error(self),
             },
 
             Self::Anyhow(_) => internal_server_error(self),
             Self::Custom(statue, _) => (statue, self.to_string()),
+            Self::Problem(problem) => return problem.into_response(),
+            Self::App(error) => return error.problem().into_response(),
         };
 
-        (status, Json(ErrorResponse { message })).into_response()
+        Problem::new(status).with_detail(message).into_response()
+    }
+}
+
+/// [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) problem details.
+///
+/// Every [`enum@Error`] response carries one as a response extension. Routers
+/// wrapped in [`problem_details`] render it as `application/problem+json`,
+/// the others keep the [`ErrorResponse`] body.
+///
+/// [`problem_details`]: crate::middleware::problem::problem_details
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    /// URI identifying the problem type, `about:blank` by default.
+    #[serde(rename = "type")]
+    pub type_uri: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Filled from the `x-request-id` header when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Extension members, serialized next to the standard ones.
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl Problem {
+    /// Creates an `about:blank` problem titled with the reason phrase of `status`.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            type_uri: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    pub fn with_type(mut self, type_uri: impl Into<String>) -> Self {
+        self.type_uri = type_uri.into();
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Sets the detail, left out when it only repeats the title.
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        let detail = detail.into();
+        self.detail = (detail != self.title).then_some(detail);
+        self
+    }
+
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Sets the stable `code` member, e.g. `USER_NOT_FOUND`.
+    pub fn with_code(self, code: impl Into<String>) -> Self {
+        self.with_extension("code", code.into())
+    }
+
+    /// Adds an extension member, e.g. `("balance", 30)`. Values that fail to
+    /// serialize are stored as `null`.
+    pub fn with_extension(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        self.extensions
+            .insert(name.into(), serde_json::to_value(value).unwrap_or_default());
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// The `code` member, or else the reason phrase of the status in upper
+    /// snake case, e.g. `NOT_FOUND`.
+    pub fn code(&self) -> String {
+        self.extension_str("code")
+            .unwrap_or_else(|| status_code(self.status()))
+    }
+
+    fn extension_str(&self, name: &str) -> Option<String> {
+        self.extensions
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    /// Message of the [`ErrorResponse`] body: the detail, or else the title.
+    fn message(&self) -> &str {
+        self.detail.as_deref().unwrap_or(&self.title)
+    }
+}
+
+impl From<&Problem> for ErrorResponse {
+    fn from(problem: &Problem) -> Self {
+        Self {
+            message: problem.message().to_string(),
+            errors: problem
+                .extensions
+                .get("errors")
+                .and_then(|errors| serde_json::from_value(errors.clone()).ok())
+                .unwrap_or_default(),
+            code: problem.code(),
+            constraint: problem.extension_str("constraint"),
+        }
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(mut self) -> Response {
+        let code = self.code();
+        self.extensions
+            .insert("code".to_string(), Value::String(code));
+        let body = ErrorResponse::from(&self);
+        let mut response = (self.status(), Json(body)).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Answers an extractor rejection with its message. Server errors, e.g. a
+/// missing extension, point at a bug in the application and are logged.
+fn rejection_response(status: StatusCode, code: Option<&str>, message: String) -> Response {
+    if status.is_server_error() {
+        tracing::error!("{}", message);
+    }
+    let mut problem = Problem::new(status).with_detail(message);
+    if let Some(code) = code {
+        problem = problem.with_code(code);
+    }
+    problem.into_response()
+}
+
+/// Default code of a status: its reason phrase in upper snake case, e.g.
+/// `NOT_FOUND`.
+fn status_code(status: StatusCode) -> String {
+    match status.canonical_reason() {
+        Some(reason) => reason
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_ascii_uppercase)
+            .collect::<Vec<_>>()
+            .join("_"),
+        None => format!("HTTP_{}", status.as_u16()),
     }
 }
 
@@ -98,3 +577,108 @@ fn internal_server_error<E: std::fmt::Display>(err: E) -> (StatusCode, String) {
         "Internal Server Error".to_string(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use validator::{Validate, ValidationError};
+
+    #[derive(Validate)]
+    #[validate(schema(function = "validate_address", skip_on_field_errors = false))]
+    struct Address {
+        #[validate(length(min = 1))]
+        city: String,
+        zip: String,
+    }
+
+    fn validate_address(address: &Address) -> Result<(), ValidationError> {
+        if address.zip.is_empty() {
+            return Err(ValidationError::new("zip_required"));
+        }
+        Ok(())
+    }
+
+    #[derive(Validate)]
+    struct Item {
+        #[validate(length(min = 1, message = "Can not be empty"))]
+        name: String,
+    }
+
+    #[derive(Validate)]
+    #[validate(schema(function = "validate_order", skip_on_field_errors = false))]
+    struct Order {
+        #[validate(nested)]
+        address: Address,
+        #[validate(nested)]
+        items: Vec<Item>,
+    }
+
+    fn validate_order(order: &Order) -> Result<(), ValidationError> {
+        if order.items.len() > 1 {
+            return Err(ValidationError::new("single_item").with_message("One item only".into()));
+        }
+        Ok(())
+    }
+
+    fn order_errors() -> Vec<FieldError> {
+        let order = Order {
+            address: Address {
+                city: String::new(),
+                zip: String::new(),
+            },
+            items: vec![
+                Item {
+                    name: String::new(),
+                },
+                Item {
+                    name: "book".to_string(),
+                },
+            ],
+        };
+        field_errors(&order.validate().unwrap_err())
+    }
+
+    #[test]
+    fn field_errors_paths() {
+        let fields: Vec<_> = order_errors()
+            .into_iter()
+            .map(|error| (error.field, error.code))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("", "single_item"),
+                ("address", "zip_required"),
+                ("address.city", "length"),
+                ("items[0].name", "length"),
+            ]
+            .map(|(field, code)| (field.to_string(), code.to_string()))
+        );
+    }
+
+    #[test]
+    fn field_errors_drop_value() {
+        let errors = order_errors();
+        let city = errors
+            .iter()
+            .find(|error| error.field == "address.city")
+            .unwrap();
+        assert_eq!(Value::Object(city.params.clone()), json!({ "min": 1 }));
+        assert_eq!(city.message, None);
+        let name = errors
+            .iter()
+            .find(|error| error.field == "items[0].name")
+            .unwrap();
+        assert_eq!(name.message.as_deref(), Some("Can not be empty"));
+    }
+
+    #[test]
+    fn summarize_field_errors() {
+        assert_eq!(
+            summarize(&order_errors()),
+            "One item only, address: zip_required, address.city: length, \
+             items[0].name: Can not be empty"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use super::Problem;
+use axum::http::StatusCode;
+use std::{collections::BTreeMap, sync::RwLock, time::Duration};
+
+/// Status and message answered for a database error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseErrorMapping {
+    pub status: StatusCode,
+    pub message: String,
+    /// Stable code of the response, e.g. `EMAIL_TAKEN`. Defaults to the
+    /// reason phrase of the status.
+    pub code: Option<String>,
+    /// Sent as `Retry-After`, in whole seconds.
+    pub retry_after: Option<Duration>,
+}
+
+impl DatabaseErrorMapping {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            code: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+}
+
+/// SQLSTATE codes answered with something more specific than a `500`.
+/// Entries added with [`map_sqlstate`] take precedence.
+const SQLSTATES: &[(&str, StatusCode, &str, &str)] = &[
+    (
+        "23502",
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "NOT_NULL_VIOLATION",
+        "Not Null Violation",
+    ),
+    (
+        "23503",
+        StatusCode::CONFLICT,
+        "FOREIGN_KEY_VIOLATION",
+        "Foreign Key Violation",
+    ),
+    (
+        "23505",
+        StatusCode::CONFLICT,
+        "UNIQUE_VIOLATION",
+        "Unique Constraint Violation",
+    ),
+    (
+        "23514",
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "CHECK_VIOLATION",
+        "Check Constraint Violation",
+    ),
+    (
+        "40001",
+        StatusCode::CONFLICT,
+        "SERIALIZATION_FAILURE",
+        "Serialization Failure",
+    ),
+    (
+        "40P01",
+        StatusCode::CONFLICT,
+        "DEADLOCK_DETECTED",
+        "Deadlock Detected",
+    ),
+];
+
+/// `Retry-After` of a `503` when no pool connection became available.
+const POOL_TIMEOUT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+static SQLSTATE_MAPPINGS: RwLock<BTreeMap<String, DatabaseErrorMapping>> =
+    RwLock::new(BTreeMap::new());
+static CONSTRAINT_MAPPINGS: RwLock<BTreeMap<String, DatabaseErrorMapping>> =
+    RwLock::new(BTreeMap::new());
+
+/// Answers database errors with SQLSTATE `code`, e.g. `23P01`, with
+/// `mapping`, replacing the built-in mapping if any.
+pub fn map_sqlstate(code: impl Into<String>, mapping: DatabaseErrorMapping) {
+    if let Ok(mut mappings) = SQLSTATE_MAPPINGS.write() {
+        mappings.insert(code.into(), mapping);
+    }
+}
+
+/// Answers violations of the constraint `name`, e.g. `users_email_key`, with
+/// `mapping`, whatever their SQLSTATE.
+pub fn map_constraint(name: impl Into<String>, mapping: DatabaseErrorMapping) {
+    if let Ok(mut mappings) = CONSTRAINT_MAPPINGS.write() {
+        mappings.insert(name.into(), mapping);
+    }
+}
+
+/// Looks up the response of `error`, or `None` for a `500`.
+///
+/// The constraint name is added as the `constraint` member of the problem.
+/// It is taken from the error fields rather than the database message, which
+/// may echo the rejected values, and left out unless it is a plain
+/// identifier.
+pub(crate) fn problem(error: &sqlx::Error) -> Option<(Problem, Option<Duration>)> {
+    let (mapping, constraint) = match error {
+        sqlx::Error::RowNotFound => (
+            DatabaseErrorMapping::new(StatusCode::NOT_FOUND, "Database Record Not Found")
+                .with_code("RECORD_NOT_FOUND"),
+            None,
+        ),
+        sqlx::Error::PoolTimedOut => {
+            tracing::warn!("{}", error);
+            let mapping =
+                DatabaseErrorMapping::new(StatusCode::SERVICE_UNAVAILABLE, "Database Unavailable")
+                    .with_code("DATABASE_UNAVAILABLE")
+                    .with_retry_after(POOL_TIMEOUT_RETRY_AFTER);
+            (mapping, None)
+        }
+        sqlx::Error::Database(db_error) => {
+            let constraint = db_error.constraint().filter(|name| is_identifier(name));
+            let mapping = constraint
+                .and_then(|name| lookup(&CONSTRAINT_MAPPINGS, name))
+                .or_else(|| sqlstate(db_error.code()?.as_ref()))?;
+            (mapping, constraint)
+        }
+        _ => return None,
+    };
+
+    let mut problem = Problem::new(mapping.status).with_detail(mapping.message);
+    if let Some(code) = mapping.code {
+        problem = problem.with_code(code);
+    }
+    if let Some(constraint) = constraint {
+        problem = problem.with_extension("constraint", constraint);
+    }
+    Some((problem, mapping.retry_after))
+}
+
+fn sqlstate(code: &str) -> Option<DatabaseErrorMapping> {
+    lookup(&SQLSTATE_MAPPINGS, code).or_else(|| {
+        SQLSTATES
+            .iter()
+            .find(|(sqlstate, ..)| *sqlstate == code)
+            .map(|(_, status, code, message)| {
+                DatabaseErrorMapping::new(*status, *message).with_code(*code)
+            })
+    })
+}
+
+fn lookup(
+    mappings: &RwLock<BTreeMap<String, DatabaseErrorMapping>>,
+    key: &str,
+) -> Option<DatabaseErrorMapping> {
+    mappings.read().ok()?.get(key).cloned()
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 63
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
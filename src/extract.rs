@@ -0,0 +1,151 @@
+//! Drop-in replacements for the axum extractors whose rejection is [`Error`],
+//! so that extraction failures share the JSON envelope of every other error.
+
+use crate::error::Error;
+use axum::{
+    extract::{
+        rejection::{FormRejection, JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts, Request,
+    },
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use std::ops::{Deref, DerefMut};
+
+/// Typed headers for [`TypedHeader`], e.g. `headers::Authorization`.
+#[cfg(feature = "typed-header")]
+pub use axum_extra::headers;
+
+macro_rules! impl_deref {
+    ($($extractor:ident),* $(,)?) => {
+        $(
+            impl<T> Deref for $extractor<T> {
+                type Target = T;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.0
+                }
+            }
+
+            impl<T> DerefMut for $extractor<T> {
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    &mut self.0
+                }
+            }
+        )*
+    };
+}
+
+/// [`axum::Json`] rejected with [`Error`]. Also usable as a response.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+    S: Send + Sync,
+    axum::Json<T>: FromRequest<S, Rejection = JsonRejection>,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::<T>::from_request(req, state).await?;
+        Ok(Json(value))
+    }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    axum::Json<T>: IntoResponse,
+{
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// [`axum::extract::Query`] rejected with [`Error`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    S: Send + Sync,
+    axum::extract::Query<T>: FromRequestParts<S, Rejection = QueryRejection>,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+        Ok(Query(value))
+    }
+}
+
+/// [`axum::extract::Path`] rejected with [`Error`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Path<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    S: Send + Sync,
+    axum::extract::Path<T>: FromRequestParts<S, Rejection = PathRejection>,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) =
+            axum::extract::Path::<T>::from_request_parts(parts, state).await?;
+        Ok(Path(value))
+    }
+}
+
+/// [`axum::Form`] rejected with [`Error`]. Also usable as a response.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Form<T>(pub T);
+
+impl<T, S> FromRequest<S> for Form<T>
+where
+    S: Send + Sync,
+    axum::Form<T>: FromRequest<S, Rejection = FormRejection>,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Form(value) = axum::Form::<T>::from_request(req, state).await?;
+        Ok(Form(value))
+    }
+}
+
+impl<T> IntoResponse for Form<T>
+where
+    axum::Form<T>: IntoResponse,
+{
+    fn into_response(self) -> Response {
+        axum::Form(self.0).into_response()
+    }
+}
+
+/// [`axum_extra::TypedHeader`] rejected with [`Error`].
+#[cfg(feature = "typed-header")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypedHeader<T>(pub T);
+
+#[cfg(feature = "typed-header")]
+impl<T, S> FromRequestParts<S> for TypedHeader<T>
+where
+    S: Send + Sync,
+    axum_extra::TypedHeader<T>:
+        FromRequestParts<S, Rejection = axum_extra::typed_header::TypedHeaderRejection>,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum_extra::TypedHeader(value) =
+            axum_extra::TypedHeader::<T>::from_request_parts(parts, state).await?;
+        Ok(TypedHeader(value))
+    }
+}
+
+impl_deref!(Json, Query, Path, Form);
+
+#[cfg(feature = "typed-header")]
+impl_deref!(TypedHeader);
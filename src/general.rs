@@ -1,20 +1,592 @@
-use anyhow::Result;
-use axum::Router;
-use serde::Deserialize;
-use std::net::SocketAddr;
+#[cfg(feature = "tls")]
+use crate::tls::{self, TlsConfig};
 
-#[derive(Debug, Deserialize)]
+use anyhow::{anyhow, Context as _, Result};
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::Extensions,
+    response::Response,
+    serve::{IncomingStream, Listener},
+    Router,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    convert::Infallible,
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    path::PathBuf,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{net::TcpListener, task::JoinSet};
+use tokio_util::sync::CancellationToken;
+use tower::Service;
+use validator::{Validate, ValidationError};
+
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GeneralConfig {
-    pub listen: String,
+    /// One address or a list of `host:port`, `tcp://host:port`,
+    /// `unix:///path.sock`, `fd://N` and `systemd` entries.
+    ///
+    /// `fd://N` adopts an inherited listening socket and `systemd` adopts every
+    /// socket passed through `LISTEN_FDS`/`LISTEN_PID`.
+    #[serde(deserialize_with = "deserialize_listen")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "listen_schema", example = "0.0.0.0:8000")
+    )]
+    pub listen: Vec<String>,
+    /// Octal permissions applied to Unix domain sockets, e.g. `"660"`.
+    #[serde(default)]
+    #[validate(custom(function = "validate_socket_mode"))]
+    #[cfg_attr(feature = "schema", schemars(example = "660"))]
+    pub unix_socket_mode: Option<String>,
+    /// How long to wait for in-flight requests once a shutdown starts.
+    #[serde(default = "default_shutdown_timeout", with = "crate::config::duration")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::config::duration::schema")
+    )]
+    pub shutdown_timeout: Duration,
+
+    /// Serves HTTPS on TCP listeners; plain HTTP when omitted.
+    #[cfg(feature = "tls")]
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+fn default_shutdown_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn validate_socket_mode(mode: &str) -> Result<(), ValidationError> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(mode) if mode <= 0o777 => Ok(()),
+        _ => Err(ValidationError::new("unix_socket_mode")
+            .with_message("must be octal permissions such as \"660\"".into())),
+    }
+}
+
+#[cfg(feature = "schema")]
+fn listen_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({
+        "anyOf": [
+            { "type": "string" },
+            { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+        ],
+    })
+}
+
+fn deserialize_listen<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Listen {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let listen = match Listen::deserialize(deserializer)? {
+        Listen::One(listen) => vec![listen],
+        Listen::Many(listen) => listen,
+    };
+    if listen.is_empty() {
+        return Err(serde::de::Error::custom("`listen` must not be empty"));
+    }
+    Ok(listen)
+}
+
+/// Address a listener is bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "tcp://{}", addr),
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+/// A listener returned by [`bind`].
+#[derive(Debug)]
+pub enum BoundListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        /// Socket file removed once serving stops; `None` for inherited sockets.
+        cleanup: Option<SocketFile>,
+    },
+}
+
+/// Socket file created by [`bind`], removed when dropped so that it does not
+/// outlive a failed startup.
+#[derive(Debug)]
+pub struct SocketFile(PathBuf);
+
+impl SocketFile {
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.0) {
+            tracing::warn!("failed to remove {}: {}", self.0.display(), err);
+        }
+    }
+}
+
+impl BoundListener {
+    pub fn local_addr(&self) -> Result<ListenAddr> {
+        match self {
+            Self::Tcp(listener) => Ok(ListenAddr::Tcp(listener.local_addr()?)),
+            #[cfg(unix)]
+            Self::Unix { listener, .. } => Ok(ListenAddr::Unix(
+                listener
+                    .local_addr()?
+                    .as_pathname()
+                    .map(PathBuf::from)
+                    .unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Binds or adopts the listeners of every `listen` entry.
+///
+/// A `systemd` entry unsets the `LISTEN_*` environment variables, which is not
+/// thread-safe: nothing else should read or modify the environment while
+/// `bind` runs.
+pub async fn bind(config: &GeneralConfig) -> Result<Vec<BoundListener>> {
+    let mut listeners = Vec::with_capacity(config.listen.len());
+    let mut adopted = Vec::new();
+    for listen in &config.listen {
+        let bound = if listen == "systemd" {
+            systemd_fds()
+                .and_then(|fds| fds.map(|fd| adopt_once(&mut adopted, fd)).collect())
+                .with_context(|| "failed to adopt systemd sockets")?
+        } else if let Some(fd) = listen.strip_prefix("fd://") {
+            let fd = fd
+                .parse()
+                .map_err(|_| anyhow!("invalid file descriptor {:?}", fd))?;
+            vec![adopt_once(&mut adopted, fd)
+                .with_context(|| format!("failed to adopt {}", listen))?]
+        } else {
+            vec![bind_one(config, listen)
+                .await
+                .with_context(|| format!("failed to bind {}", listen))?]
+        };
+        for listener in bound {
+            tracing::debug!("listening on {}", listener.local_addr()?);
+            listeners.push(listener);
+        }
+    }
+    if listeners.is_empty() {
+        return Err(anyhow!("no listeners to serve on"));
+    }
+    Ok(listeners)
+}
+
+async fn bind_one(config: &GeneralConfig, listen: &str) -> Result<BoundListener> {
+    if let Some(path) = listen.strip_prefix("unix://") {
+        return bind_unix(config, PathBuf::from(path));
+    }
+    let addr = listen.strip_prefix("tcp://").unwrap_or(listen);
+    Ok(BoundListener::Tcp(TcpListener::bind(addr).await?))
+}
+
+/// First file descriptor passed by systemd, see `sd_listen_fds(3)`.
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// Returns the descriptors passed through `LISTEN_FDS`, provided `LISTEN_PID`
+/// names this process.
+///
+/// Like `sd_listen_fds(1)`, the `LISTEN_*` variables are unset so that child
+/// processes spawned by the application do not adopt the sockets too.
+///
+/// This runs inside the multi-threaded runtime, and modifying the environment
+/// while another thread reads it, e.g. through libc's `getenv`, is undefined
+/// behavior on most platforms. The risk is confined to startup, before any
+/// request is served, but background threads of the application or of its
+/// dependencies may already be running.
+fn systemd_fds() -> Result<std::ops::Range<i32>> {
+    let pid = std::env::var("LISTEN_PID");
+    let fds = std::env::var("LISTEN_FDS");
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(name);
+    }
+
+    let pid = pid.with_context(|| "LISTEN_PID is not set")?;
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        return Err(anyhow!("LISTEN_PID={} does not match this process", pid));
+    }
+    let fds = fds.with_context(|| "LISTEN_FDS is not set")?;
+    let count: i32 = fds
+        .parse()
+        .map_err(|_| anyhow!("invalid LISTEN_FDS {:?}", fds))?;
+    if count <= 0 {
+        return Err(anyhow!("LISTEN_FDS={} passes no sockets", fds));
+    }
+    let end = SD_LISTEN_FDS_START
+        .checked_add(count)
+        .ok_or_else(|| anyhow!("invalid LISTEN_FDS {:?}", fds))?;
+    Ok(SD_LISTEN_FDS_START..end)
+}
+
+/// Adopts `fd`, refusing to take ownership of the same descriptor twice.
+fn adopt_once(adopted: &mut Vec<i32>, fd: i32) -> Result<BoundListener> {
+    if adopted.contains(&fd) {
+        return Err(anyhow!("file descriptor {} is listed more than once", fd));
+    }
+    let listener = adopt(fd)?;
+    adopted.push(fd);
+    Ok(listener)
 }
 
-pub async fn serve(config: &GeneralConfig, router: Router) -> Result<()> {
-    let listener = tokio::net::TcpListener::bind(&config.listen).await?;
-    tracing::debug!("listening on {}", listener.local_addr()?);
-    axum::serve(
+#[cfg(unix)]
+fn adopt(fd: i32) -> Result<BoundListener> {
+    use std::os::fd::{BorrowedFd, FromRawFd};
+
+    if fd < SD_LISTEN_FDS_START {
+        return Err(anyhow!("refusing to adopt standard stream {}", fd));
+    }
+    // SAFETY: `fd` is not -1 and is only borrowed while it is checked, so a
+    // descriptor refused here is left open for its owner.
+    let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+    let family =
+        prepare_listening_socket(borrowed).with_context(|| format!("file descriptor {}", fd))?;
+
+    // SAFETY: the descriptor is a listening socket inherited for us to serve
+    // on and is adopted at most once per `bind`; from here on the listener
+    // owns it.
+    match family {
+        libc::AF_INET | libc::AF_INET6 => {
+            let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+            Ok(BoundListener::Tcp(TcpListener::from_std(listener)?))
+        }
+        _ => {
+            let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+            Ok(BoundListener::Unix {
+                listener: UnixListener::from_std(listener)?,
+                cleanup: None,
+            })
+        }
+    }
+}
+
+/// Checks that `fd` is a listening stream socket of a supported family, then
+/// makes it non-blocking and close-on-exec. Returns its address family.
+#[cfg(unix)]
+fn prepare_listening_socket(fd: std::os::fd::BorrowedFd<'_>) -> std::io::Result<libc::c_int> {
+    use std::{io, mem::MaybeUninit, os::fd::AsRawFd};
+
+    fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(result)
+    }
+
+    fn int_option(fd: libc::c_int, option: libc::c_int) -> io::Result<libc::c_int> {
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: `value` and `len` describe a valid `c_int` buffer.
+        check(unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                (&mut value as *mut libc::c_int).cast(),
+                &mut len,
+            )
+        })?;
+        Ok(value)
+    }
+
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message.to_string());
+    let fd = fd.as_raw_fd();
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+    // SAFETY: `fstat` fills `stat` when it succeeds.
+    check(unsafe { libc::fstat(fd, stat.as_mut_ptr()) })?;
+    let stat = unsafe { stat.assume_init() };
+    if stat.st_mode & libc::S_IFMT != libc::S_IFSOCK {
+        return Err(invalid("not a socket"));
+    }
+    if int_option(fd, libc::SO_TYPE)? != libc::SOCK_STREAM {
+        return Err(invalid("not a stream socket"));
+    }
+    if int_option(fd, libc::SO_ACCEPTCONN)? == 0 {
+        return Err(invalid("not a listening socket"));
+    }
+
+    // SAFETY: an all-zero `sockaddr_storage` is valid, and `len` is its size.
+    let mut addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    check(unsafe {
+        libc::getsockname(
+            fd,
+            (&mut addr as *mut libc::sockaddr_storage).cast(),
+            &mut len,
+        )
+    })?;
+    let family = libc::c_int::from(addr.ss_family);
+    if ![libc::AF_INET, libc::AF_INET6, libc::AF_UNIX].contains(&family) {
+        return Err(invalid("unsupported address family"));
+    }
+
+    // Inherited descriptors are not close-on-exec; keep them out of processes
+    // spawned by the application.
+    // SAFETY: `fcntl` only reads and sets descriptor and status flags.
+    let flags = check(unsafe { libc::fcntl(fd, libc::F_GETFD) })?;
+    check(unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) })?;
+    let flags = check(unsafe { libc::fcntl(fd, libc::F_GETFL) })?;
+    check(unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) })?;
+    Ok(family)
+}
+
+#[cfg(not(unix))]
+fn adopt(fd: i32) -> Result<BoundListener> {
+    Err(anyhow!(
+        "inherited sockets are not supported on this platform: {}",
+        fd
+    ))
+}
+
+#[cfg(unix)]
+fn bind_unix(config: &GeneralConfig, path: PathBuf) -> Result<BoundListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!("{} exists and is not a socket", path.display()));
+        }
+        // Nobody accepting on the socket means it was left behind by a previous run.
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(anyhow!("{} is already in use", path.display()));
+        }
+        std::fs::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    let cleanup = SocketFile(path);
+    if let Some(mode) = &config.unix_socket_mode {
+        let mode = u32::from_str_radix(mode, 8)
+            .map_err(|_| anyhow!("invalid `unix_socket_mode` {:?}", mode))?;
+        std::fs::set_permissions(cleanup.path(), std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(BoundListener::Unix {
         listener,
-        router.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await?;
-    Ok(())
+        cleanup: Some(cleanup),
+    })
+}
+
+#[cfg(not(unix))]
+fn bind_unix(_config: &GeneralConfig, path: PathBuf) -> Result<BoundListener> {
+    Err(anyhow!(
+        "unix domain sockets are not supported on this platform: {}",
+        path.display()
+    ))
+}
+
+/// Serves `router` on every listener until `signal` resolves, then waits up
+/// to `shutdown_timeout` for in-flight requests to complete.
+///
+/// TCP connections are terminated with TLS when `[general.tls]` is configured.
+/// Unix domain socket files created by [`bind`] are removed once serving stops.
+pub async fn serve<F>(
+    config: &GeneralConfig,
+    listeners: Vec<BoundListener>,
+    router: Router,
+    signal: F,
+) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    if listeners.is_empty() {
+        return Err(anyhow!("no listeners to serve on"));
+    }
+    let draining = CancellationToken::new();
+    let mut servers = JoinSet::new();
+    let mut socket_files = Vec::new();
+    for listener in listeners {
+        let router = router.clone();
+        let draining = draining.clone();
+        match listener {
+            BoundListener::Tcp(listener) => {
+                #[cfg(feature = "tls")]
+                if let Some(tls_config) = &config.tls {
+                    let listener = tls::TlsListener::new(listener, tls_config)?;
+                    servers.spawn(serve_with(listener, router, draining));
+                    continue;
+                }
+                servers.spawn(serve_with(listener, router, draining));
+            }
+            #[cfg(unix)]
+            BoundListener::Unix { listener, cleanup } => {
+                socket_files.extend(cleanup);
+                servers.spawn(serve_with(listener, router, draining));
+            }
+        }
+    }
+
+    let drain_timeout = config.shutdown_timeout;
+    let result = tokio::select! {
+        result = async {
+            while let Some(result) = servers.join_next().await {
+                // One listener failing takes the others down with it.
+                if let Err(err) = result? {
+                    draining.cancel();
+                    return Err(err.into());
+                }
+            }
+            Ok(())
+        } => result,
+        _ = async {
+            tokio::select! {
+                _ = signal => draining.cancel(),
+                _ = draining.cancelled() => {}
+            }
+            tokio::time::sleep(drain_timeout).await;
+        } => {
+            tracing::warn!(
+                "graceful shutdown timed out after {:?}, dropping remaining connections",
+                drain_timeout
+            );
+            Ok(())
+        }
+    };
+
+    drop(socket_files);
+    result
+}
+
+async fn serve_with<L: Accept>(
+    listener: L,
+    router: Router,
+    draining: CancellationToken,
+) -> std::io::Result<()> {
+    axum::serve(listener, MakeService { router })
+        .with_graceful_shutdown(draining.cancelled_owned())
+        .await
+}
+
+/// Peer of a Unix domain socket connection, inserted as
+/// `ConnectInfo<UnixPeer>` where TCP connections get `ConnectInfo<SocketAddr>`.
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct UnixPeer {
+    /// Socket the connection was accepted on.
+    pub path: Option<PathBuf>,
+    pub pid: Option<i32>,
+    pub uid: Option<u32>,
+}
+
+#[cfg(unix)]
+impl fmt::Display for UnixPeer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unix:")?;
+        if let Some(path) = &self.path {
+            write!(f, "{}", path.display())?;
+        }
+        if let Some(pid) = self.pid {
+            write!(f, " pid={}", pid)?;
+        }
+        if let Some(uid) = self.uid {
+            write!(f, " uid={}", uid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Listeners whose connections expose per-connection request extensions,
+/// such as the peer address.
+pub(crate) trait Accept: Listener<Addr: fmt::Debug> {
+    fn extensions(stream: &IncomingStream<'_, Self>) -> Extensions
+    where
+        Self: Sized;
+}
+
+impl Accept for TcpListener {
+    fn extensions(stream: &IncomingStream<'_, Self>) -> Extensions {
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(*stream.remote_addr()));
+        extensions
+    }
+}
+
+#[cfg(unix)]
+impl Accept for UnixListener {
+    fn extensions(stream: &IncomingStream<'_, Self>) -> Extensions {
+        let cred = stream.io().peer_cred().ok();
+        let peer = UnixPeer {
+            path: stream
+                .io()
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(PathBuf::from)),
+            pid: cred.and_then(|cred| cred.pid()),
+            uid: cred.map(|cred| cred.uid()),
+        };
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(peer));
+        extensions
+    }
+}
+
+#[derive(Clone)]
+struct MakeService {
+    router: Router,
+}
+
+impl<L: Accept> Service<IncomingStream<'_, L>> for MakeService {
+    type Response = ConnectionService;
+    type Error = Infallible;
+    type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, stream: IncomingStream<'_, L>) -> Self::Future {
+        std::future::ready(Ok(ConnectionService {
+            router: self.router.clone(),
+            extensions: L::extensions(&stream),
+        }))
+    }
+}
+
+/// Router for a single connection, tagging each request with the
+/// connection's extensions.
+#[derive(Clone)]
+struct ConnectionService {
+    router: Router,
+    extensions: Extensions,
+}
+
+impl Service<Request> for ConnectionService {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = <Router as Service<Request>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Service::<Request>::poll_ready(&mut self.router, cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        request.extensions_mut().extend(self.extensions.clone());
+        self.router.call(request)
+    }
 }
@@ -0,0 +1,223 @@
+//! Localized error messages, looked up by error code and selected by the
+//! request's `Accept-Language`.
+//!
+//! A catalog holds one flat TOML table per locale, mapping codes to messages.
+//! `{name}` placeholders are replaced with the extension members of the
+//! [`Problem`], e.g. `{constraint}`:
+//!
+//! ```toml
+//! # locales/zh-CN.toml
+//! NOT_FOUND = "资源不存在"
+//! UNIQUE_VIOLATION = "数据重复：{constraint}"
+//! USER_NOT_FOUND = "用户不存在"
+//! ```
+
+use crate::error::Problem;
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path};
+
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    /// Messages by code, keyed by the lowercase locale tag.
+    locales: BTreeMap<String, Locale>,
+}
+
+#[derive(Debug, Clone)]
+struct Locale {
+    tag: String,
+    messages: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every `<locale>.toml` file of `directory`, e.g.
+    /// `locales/en.toml` and `locales/zh-CN.toml`.
+    pub fn from_dir(directory: impl AsRef<Path>) -> Result<Self> {
+        let directory = directory.as_ref();
+        let mut catalog = Self::new();
+        let entries = std::fs::read_dir(directory)
+            .with_context(|| format!("failed to read {}", directory.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("toml") {
+                continue;
+            }
+            let locale = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| anyhow!("invalid locale file name {}", path.display()))?;
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            catalog = catalog
+                .with_toml(locale, &content)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+        }
+        Ok(catalog)
+    }
+
+    /// Adds the messages of a TOML table of codes to messages for `locale`,
+    /// e.g. `zh-CN`.
+    pub fn with_toml(self, locale: &str, content: &str) -> Result<Self> {
+        let messages: BTreeMap<String, String> = toml::from_str(content)?;
+        Ok(self.with_messages(locale, messages))
+    }
+
+    /// Adds messages by code for `locale`, replacing those already set.
+    pub fn with_messages<K, V>(
+        mut self,
+        locale: &str,
+        messages: impl IntoIterator<Item = (K, V)>,
+    ) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let entry = self
+            .locales
+            .entry(locale.to_ascii_lowercase())
+            .or_insert_with(|| Locale {
+                tag: locale.to_string(),
+                messages: BTreeMap::new(),
+            });
+        entry.messages.extend(
+            messages
+                .into_iter()
+                .map(|(code, message)| (code.into(), message.into())),
+        );
+        self
+    }
+
+    /// Picks the locale that best matches an `Accept-Language` value, trying
+    /// each language by decreasing quality. A language matches a locale of
+    /// the same tag, or else of the same primary language, so `zh-TW` falls
+    /// back to `zh` or `zh-CN`.
+    pub fn negotiate(&self, accept_language: &str) -> Option<&str> {
+        let mut languages: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|quality| quality.trim().parse().ok())
+                    .unwrap_or(1.0);
+                (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so languages of equal quality keep their order.
+        languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        languages.into_iter().find_map(|(tag, _)| {
+            let tag = tag.to_ascii_lowercase();
+            let primary = primary_language(&tag);
+            self.locales
+                .get(&tag)
+                .or_else(|| self.locales.get(primary))
+                .or_else(|| {
+                    self.locales
+                        .iter()
+                        .find(|(locale, _)| primary_language(locale) == primary)
+                        .map(|(_, locale)| locale)
+                })
+                .map(|locale| locale.tag.as_str())
+        })
+    }
+
+    /// Returns the message of `code` in `locale`, without placeholders
+    /// replaced.
+    pub fn message(&self, locale: &str, code: &str) -> Option<&str> {
+        self.locales
+            .get(&locale.to_ascii_lowercase())?
+            .messages
+            .get(code)
+            .map(String::as_str)
+    }
+
+    /// Replaces the detail of `problem` with its message in `locale`.
+    /// Returns whether the catalog has a message for its code.
+    pub fn localize(&self, locale: &str, problem: &mut Problem) -> bool {
+        let Some(message) = self.message(locale, &problem.code()) else {
+            return false;
+        };
+        let mut message = message.to_string();
+        for (name, value) in &problem.extensions {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Number(_) | Value::Bool(_) => value.to_string(),
+                _ => continue,
+            };
+            message = message.replace(&format!("{{{}}}", name), &value);
+        }
+        problem.detail = Some(message);
+        true
+    }
+}
+
+fn primary_language(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    fn catalog() -> Catalog {
+        Catalog::new()
+            .with_messages("en", [("NOT_FOUND", "Not found")])
+            .with_messages("zh-CN", [("NOT_FOUND", "资源不存在")])
+            .with_messages("fr", [("NOT_FOUND", "Introuvable")])
+    }
+
+    #[test]
+    fn negotiate_by_quality() {
+        let catalog = catalog();
+        assert_eq!(catalog.negotiate("fr;q=0.5, en;q=0.8"), Some("en"));
+        assert_eq!(catalog.negotiate("de, fr;q=0.9, en;q=0.9"), Some("fr"));
+        assert_eq!(catalog.negotiate("ZH-cn"), Some("zh-CN"));
+        assert_eq!(catalog.negotiate("de"), None);
+    }
+
+    #[test]
+    fn negotiate_primary_language() {
+        let catalog = catalog();
+        assert_eq!(catalog.negotiate("zh-TW"), Some("zh-CN"));
+        assert_eq!(catalog.negotiate("en-GB, fr"), Some("en"));
+    }
+
+    #[test]
+    fn negotiate_skips_wildcard_and_zero_quality() {
+        let catalog = catalog();
+        assert_eq!(catalog.negotiate("*"), None);
+        assert_eq!(catalog.negotiate("en;q=0, *;q=0.5"), None);
+        assert_eq!(catalog.negotiate("en;q=0, fr;q=0.1"), Some("fr"));
+    }
+
+    #[test]
+    fn localize_replaces_placeholders() {
+        let catalog = Catalog::new().with_messages(
+            "zh-CN",
+            [(
+                "UNIQUE_VIOLATION",
+                "数据重复：{constraint}，共 {count} 条，{missing}",
+            )],
+        );
+        let mut problem = Problem::new(StatusCode::CONFLICT)
+            .with_code("UNIQUE_VIOLATION")
+            .with_extension("constraint", "users_email_key")
+            .with_extension("count", 2);
+        assert!(catalog.localize("zh-CN", &mut problem));
+        assert_eq!(
+            problem.detail.as_deref(),
+            Some("数据重复：users_email_key，共 2 条，{missing}")
+        );
+
+        let mut problem = Problem::new(StatusCode::NOT_FOUND).with_code("USER_NOT_FOUND");
+        assert!(!catalog.localize("zh-CN", &mut problem));
+        assert_eq!(problem.detail, None);
+    }
+}
@@ -1,15 +1,39 @@
+pub mod admin;
 pub mod bootstrap;
 pub mod config;
+pub mod context;
 pub mod error;
+pub mod extract;
 pub mod general;
 pub mod logger;
 pub mod middleware;
+pub mod reload;
+pub mod shutdown;
 pub mod validation;
 
+#[cfg(feature = "cli")]
+pub mod cli;
+
+#[cfg(feature = "i18n")]
+pub mod i18n;
+
 #[cfg(feature = "postgres")]
 pub mod postgres;
 
 #[cfg(feature = "redis")]
 pub mod redis;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "tls")]
+pub mod tls;
+
 pub type AppResult<T> = Result<T, error::Error>;
+
+/// Used by the code generated by `#[derive(AppError)]`.
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+    pub use axum::http::StatusCode;
+}
@@ -1,21 +1,60 @@
-use anyhow::Result;
-use serde::Deserialize;
-use std::io::Write;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{io::Write, sync::OnceLock};
 use tracing::Level;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{
-    fmt::time::ChronoLocal, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
+    fmt::time::ChronoLocal, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter,
+    Registry,
 };
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, Deserialize)]
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+#[validate(schema(function = "validate_file_writer"))]
 pub struct LoggerConfig {
+    /// Most verbose level recorded, overridden per target by `RUST_LOG`.
     pub level: LogLevel,
+    /// `file` writes to `directory/file_name_prefix.year-month-day`,
+    /// `stdout` to the console.
     pub writer: LogWriter,
+    /// Only used with `writer = "file"`.
     pub directory: String,
+    /// Only used with `writer = "file"`.
     pub file_name_prefix: String,
 }
 
-#[derive(Debug, Deserialize)]
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            writer: LogWriter::Stdout,
+            directory: "./log".to_string(),
+            file_name_prefix: "axum_kit.log".to_string(),
+        }
+    }
+}
+
+fn validate_file_writer(config: &LoggerConfig) -> Result<(), ValidationError> {
+    if !matches!(config.writer, LogWriter::File) {
+        return Ok(());
+    }
+    if config.directory.is_empty() {
+        return Err(ValidationError::new("directory")
+            .with_message("`directory` must not be empty with `writer = \"file\"`".into()));
+    }
+    if config.file_name_prefix.is_empty() {
+        return Err(ValidationError::new("file_name_prefix")
+            .with_message("`file_name_prefix` must not be empty with `writer = \"file\"`".into()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum LogLevel {
     #[serde(rename = "trace")]
     Trace,
@@ -29,7 +68,8 @@ pub enum LogLevel {
     Error,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum LogWriter {
     #[serde(rename = "file")]
     File,
@@ -63,16 +103,55 @@ pub fn init(config: &LoggerConfig) -> Result<WorkerGuard> {
     };
     let (non_blocking, worker_guard) = tracing_appender::non_blocking(writer);
 
-    let filter =
-        EnvFilter::from_default_env().add_directive(config.level.to_tracing_level().into());
+    let (filter, handle) = reload::Layer::new(level_filter(config.level));
 
     let layer = tracing_subscriber::fmt::layer()
         .with_ansi(ansi)
         .with_timer(ChronoLocal::new("%Y-%m-%d %H:%M:%S".to_string()))
         .with_writer(non_blocking);
-    tracing_subscriber::registry()
+    // Several applications may share a process (e.g. in tests); only the first
+    // one installs the global subscriber.
+    if tracing_subscriber::registry()
         .with(filter)
         .with(layer)
-        .init();
+        .try_init()
+        .is_err()
+    {
+        tracing::debug!("global subscriber already set, keeping the existing one");
+    } else {
+        let _ = FILTER_HANDLE.set(handle);
+    }
     Ok(worker_guard)
 }
+
+/// Returns the active filter directives, if the global subscriber was
+/// installed by [`init`].
+pub fn current_filter() -> Option<String> {
+    FILTER_HANDLE
+        .get()?
+        .with_current(|filter| filter.to_string())
+        .ok()
+}
+
+fn level_filter(level: LogLevel) -> EnvFilter {
+    EnvFilter::from_default_env().add_directive(level.to_tracing_level().into())
+}
+
+/// Resets the active filter to `RUST_LOG` plus `level`, as set up by [`init`].
+pub fn set_level(level: LogLevel) -> Result<()> {
+    FILTER_HANDLE
+        .get()
+        .ok_or_else(|| anyhow!("logger not initialized by axum-kit"))?
+        .reload(level_filter(level))?;
+    Ok(())
+}
+
+/// Replaces the active filter with `directives`, e.g. `"info,axum_kit=debug"`.
+pub fn set_filter(directives: &str) -> Result<()> {
+    let filter = EnvFilter::try_new(directives)?;
+    FILTER_HANDLE
+        .get()
+        .ok_or_else(|| anyhow!("logger not initialized by axum-kit"))?
+        .reload(filter)?;
+    Ok(())
+}
@@ -1,5 +1,134 @@
-use tower_http::cors::CorsLayer;
+use crate::config::Config;
+use arc_swap::ArcSwap;
+use axum::http::HeaderValue;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use validator::{Validate, ValidationError};
 
+#[derive(Debug, Default, Deserialize, Serialize, Validate)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Origins allowed to make cross-origin requests, e.g.
+    /// `https://example.com`. Every origin is allowed when empty.
+    #[validate(custom(function = "validate_origins"))]
+    pub allow_origins: Vec<String>,
+}
+
+fn validate_origins(origins: &[String]) -> Result<(), ValidationError> {
+    if origins
+        .iter()
+        .any(|origin| HeaderValue::from_str(origin).is_err())
+    {
+        return Err(ValidationError::new("allow_origins")
+            .with_message("origins must be valid header values".into()));
+    }
+    Ok(())
+}
+
+impl CorsConfig {
+    /// Whether `origin` may make cross-origin requests.
+    pub fn allows(&self, origin: &HeaderValue) -> bool {
+        self.allow_origins.is_empty()
+            || self
+                .allow_origins
+                .iter()
+                .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+    }
+}
+
+/// Checks origins against the `[cors]` section of the current configuration
+/// of one application. Inserted into its requests by
+/// [`Application`](crate::bootstrap::Application).
+#[derive(Clone)]
+pub(crate) struct AllowedOrigins(Arc<dyn Fn(&HeaderValue) -> bool + Send + Sync>);
+
+impl AllowedOrigins {
+    pub(crate) fn new<Ext>(config: Arc<ArcSwap<Config<Ext>>>) -> Self
+    where
+        Ext: Send + Sync + 'static,
+    {
+        Self(Arc::new(move |origin| config.load().cors.allows(origin)))
+    }
+}
+
+/// Permissive CORS, restricted to `[cors] allow_origins` of the application
+/// serving the request when configured. The origins are read on every
+/// request, so a configuration reload applies to existing routers. Every
+/// origin is allowed outside an [`Application`](crate::bootstrap::Application).
 pub fn cors() -> CorsLayer {
-    CorsLayer::permissive()
+    CorsLayer::permissive().allow_origin(AllowOrigin::predicate(|origin, parts| {
+        parts
+            .extensions
+            .get::<AllowedOrigins>()
+            .map_or(true, |allowed| (allowed.0)(origin))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header, Request},
+        routing::get,
+        Extension, Router,
+    };
+    use tower::ServiceExt;
+
+    fn router(allow_origins: &[&str]) -> Router {
+        let config = CorsConfig {
+            allow_origins: allow_origins
+                .iter()
+                .map(|origin| origin.to_string())
+                .collect(),
+        };
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(cors())
+            .layer(Extension(AllowedOrigins(Arc::new(move |origin| {
+                config.allows(origin)
+            }))))
+    }
+
+    async fn allowed_origin(router: Router, origin: &str) -> Option<HeaderValue> {
+        let request = Request::get("/")
+            .header(header::ORIGIN, origin)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .cloned()
+    }
+
+    #[tokio::test]
+    async fn origins_are_per_router() {
+        let example = router(&["https://example.com"]);
+        let other = router(&["https://other.example"]);
+        assert_eq!(
+            allowed_origin(example.clone(), "https://example.com").await,
+            Some(HeaderValue::from_static("https://example.com"))
+        );
+        assert_eq!(allowed_origin(example, "https://other.example").await, None);
+        assert_eq!(
+            allowed_origin(other, "https://other.example").await,
+            Some(HeaderValue::from_static("https://other.example"))
+        );
+    }
+
+    #[tokio::test]
+    async fn every_origin_allowed_by_default() {
+        assert!(allowed_origin(router(&[]), "https://any.example")
+            .await
+            .is_some());
+        let standalone = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(cors());
+        assert!(allowed_origin(standalone, "https://any.example")
+            .await
+            .is_some());
+    }
 }
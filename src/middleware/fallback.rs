@@ -0,0 +1,225 @@
+use crate::error::{Error, Problem};
+use axum::{
+    body::{Body, HttpBody},
+    http::{header, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Router,
+};
+use futures_util::future::BoxFuture;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Set once a request reaches one of the routes of the router, as opposed to
+/// its fallback.
+#[derive(Clone, Default)]
+struct Routed(Arc<AtomicBool>);
+
+/// Marks requests that reach a route, see [`Routed`].
+#[derive(Debug, Clone, Default)]
+struct MarkRoutedLayer;
+
+impl<S> Layer<S> for MarkRoutedLayer {
+    type Service = MarkRouted<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MarkRouted { inner }
+    }
+}
+
+#[derive(Clone)]
+struct MarkRouted<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for MarkRouted<S>
+where
+    S: Service<Request<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if let Some(routed) = request.extensions().get::<Routed>() {
+            routed.0.store(true, Ordering::Relaxed);
+        }
+        self.inner.call(request)
+    }
+}
+
+/// Gives the empty `404` of the router's fallback and the empty `405` of a
+/// method fallback the body of [`Error`].
+#[derive(Debug, Clone, Default)]
+struct ErrorFallbackLayer;
+
+impl<S> Layer<S> for ErrorFallbackLayer {
+    type Service = ErrorFallback<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ErrorFallback { inner }
+    }
+}
+
+#[derive(Clone)]
+struct ErrorFallback<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for ErrorFallback<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let routed = Routed::default();
+        request.extensions_mut().insert(routed.clone());
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            let error = match response.status() {
+                StatusCode::NOT_FOUND if !routed.0.load(Ordering::Relaxed) => Error::NotFound,
+                // axum sets `Allow` on the responses of method fallbacks only.
+                StatusCode::METHOD_NOT_ALLOWED
+                    if response.headers().contains_key(header::ALLOW) =>
+                {
+                    Problem::new(StatusCode::METHOD_NOT_ALLOWED).into()
+                }
+                _ => return Ok(response),
+            };
+            if response.extensions().get::<Problem>().is_some()
+                || response.body().size_hint().exact() != Some(0)
+            {
+                return Ok(response);
+            }
+
+            // Keep the headers set by the layers of the router, e.g. `Allow`,
+            // `x-request-id` and CORS.
+            let (mut parts, _) = response.into_parts();
+            let (error_parts, body) = error.into_response().into_parts();
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts.headers.extend(error_parts.headers);
+            parts.extensions.extend(error_parts.extensions);
+            Ok(Response::from_parts(parts, body))
+        })
+    }
+}
+
+/// Answers unknown paths with the `404` of [`Error::NotFound`] and wrong
+/// methods with a `405`, keeping the `Allow` header. Only the empty responses
+/// of axum's default fallbacks are replaced: responses of handlers, such as a
+/// bare `StatusCode::NOT_FOUND`, and fallbacks set on the router that answer
+/// with a body are left as they are.
+///
+/// The router is wrapped as a whole, so its own layers, e.g. request ids,
+/// tracing or CORS, still apply to these responses. [`Application`] calls it
+/// unless
+/// [`without_error_fallback`](crate::bootstrap::Application::without_error_fallback)
+/// is called.
+///
+/// [`Application`]: crate::bootstrap::Application
+pub fn error_fallback(router: Router) -> Router {
+    let router = if router.has_routes() {
+        router.route_layer(MarkRoutedLayer)
+    } else {
+        router
+    };
+    Router::new().fallback_service(ErrorFallbackLayer.layer(router))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{request_id, X_REQUEST_ID};
+    use axum::routing::get;
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use tower::{ServiceBuilder, ServiceExt};
+
+    fn router() -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route("/gone", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/own",
+                get(|| async { "ok" })
+                    .fallback(|| async { (StatusCode::METHOD_NOT_ALLOWED, "use GET") }),
+            )
+            .layer(
+                ServiceBuilder::new()
+                    .layer(request_id::set_request_id())
+                    .layer(request_id::propagate_request_id()),
+            )
+    }
+
+    async fn send(router: Router, method: &str, uri: &str) -> (Response, Option<Value>) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = error_fallback(router).oneshot(request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await.unwrap().to_bytes();
+        let json = serde_json::from_slice(&body).ok();
+        (Response::from_parts(parts, Body::from(body)), json)
+    }
+
+    #[tokio::test]
+    async fn unknown_path() {
+        let (response, body) = send(router(), "GET", "/nope").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().contains_key(X_REQUEST_ID));
+        assert_eq!(
+            body,
+            Some(json!({ "message": "Not Found", "code": "NOT_FOUND" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn unsupported_method() {
+        let (response, body) = send(router(), "POST", "/").await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[header::ALLOW], "GET,HEAD");
+        assert!(response.headers().contains_key(X_REQUEST_ID));
+        assert_eq!(body.unwrap()["code"], "METHOD_NOT_ALLOWED");
+    }
+
+    #[tokio::test]
+    async fn handler_responses_are_kept() {
+        let (response, body) = send(router(), "GET", "/gone").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body, None);
+
+        let (response, _) = send(router(), "POST", "/own").await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "use GET");
+    }
+
+    #[tokio::test]
+    async fn custom_fallback_is_kept() {
+        let router = router().fallback(|| async { "spa index.html" });
+        let (response, _) = send(router, "GET", "/nope").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "spa index.html");
+    }
+}
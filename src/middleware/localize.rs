@@ -0,0 +1,141 @@
+use super::problem::APPLICATION_PROBLEM_JSON;
+use crate::{
+    error::{ErrorResponse, Problem},
+    i18n::Catalog,
+};
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Request},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Localizes the message of error responses with a [`Catalog`].
+#[derive(Debug, Clone)]
+pub struct LocalizeLayer {
+    catalog: Arc<Catalog>,
+}
+
+impl<S> Layer<S> for LocalizeLayer {
+    type Service = Localize<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Localize {
+            inner,
+            catalog: self.catalog.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Localize<S> {
+    inner: S,
+    catalog: Arc<Catalog>,
+}
+
+impl<S> Service<Request<Body>> for Localize<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let locale = request
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| self.catalog.negotiate(value))
+            .map(str::to_string);
+        let catalog = self.catalog.clone();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            let Some(locale) = locale else {
+                return Ok(response);
+            };
+            let Some(mut problem) = response.extensions_mut().remove::<Problem>() else {
+                return Ok(response);
+            };
+            if !catalog.localize(&locale, &mut problem) {
+                response.extensions_mut().insert(problem);
+                return Ok(response);
+            }
+
+            // Either layer may run first, so keep the format already rendered.
+            let is_problem_json = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .is_some_and(|value| value == APPLICATION_PROBLEM_JSON);
+            let body = if is_problem_json {
+                serde_json::to_vec(&problem)
+            } else {
+                serde_json::to_vec(&ErrorResponse::from(&problem))
+            };
+            if let Ok(body) = body {
+                *response.body_mut() = Body::from(body);
+                let headers = response.headers_mut();
+                headers.remove(header::CONTENT_LENGTH);
+                headers.remove(header::CONTENT_ENCODING);
+                if let Ok(locale) = HeaderValue::from_str(&locale) {
+                    headers.insert(header::CONTENT_LANGUAGE, locale);
+                }
+            }
+            response.extensions_mut().insert(problem);
+            Ok(response)
+        })
+    }
+}
+
+/// Replaces the message of error responses with its translation in the
+/// language of `Accept-Language`, looked up by error code. Responses keep
+/// their message when no catalog locale matches or the code is missing.
+pub fn localize(catalog: Catalog) -> LocalizeLayer {
+    LocalizeLayer {
+        catalog: Arc::new(catalog),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, middleware::compression::compression};
+    use axum::{routing::get, Router};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn replaces_compressed_body() {
+        let catalog = Catalog::new().with_messages("zh-CN", [("NOT_FOUND", "资源不存在")]);
+        let router = Router::new()
+            .route("/", get(|| async { Error::NotFound }))
+            .layer(compression())
+            .layer(localize(catalog));
+        let request = Request::get("/")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .header(header::ACCEPT_LANGUAGE, "zh-CN")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.headers().get(header::CONTENT_ENCODING), None);
+        assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "zh-CN");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "资源不存在");
+        assert_eq!(body["code"], "NOT_FOUND");
+    }
+}
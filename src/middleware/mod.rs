@@ -1,9 +1,14 @@
 pub mod compression;
 pub mod cors;
+pub mod fallback;
+pub mod problem;
 pub mod request_id;
 pub mod trace;
 pub mod trace_body;
 
+#[cfg(feature = "i18n")]
+pub mod localize;
+
 pub const DIRECT_CONNECT_IP: &str = "direct-connect-ip";
 pub const X_FORWARDED_FOR: &str = "x-forwarded-for";
 pub const X_REAL_IP: &str = "x-real-ip";
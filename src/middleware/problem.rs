@@ -0,0 +1,127 @@
+use super::X_REQUEST_ID;
+use crate::error::Problem;
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, Request},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+pub(crate) const APPLICATION_PROBLEM_JSON: &str = "application/problem+json";
+
+/// Renders the [`Problem`] attached to error responses as
+/// `application/problem+json`.
+#[derive(Debug, Clone, Default)]
+pub struct ProblemDetailsLayer;
+
+impl<S> Layer<S> for ProblemDetailsLayer {
+    type Service = ProblemDetails<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ProblemDetails { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct ProblemDetails<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for ProblemDetails<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let request_id = header_request_id(request.headers());
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            let Some(mut problem) = response.extensions_mut().remove::<Problem>() else {
+                return Ok(response);
+            };
+            // The request id may also be set by a layer inside this one.
+            if problem.instance.is_none() {
+                problem.instance = request_id.or_else(|| header_request_id(response.headers()));
+            }
+            let Ok(body) = serde_json::to_vec(&problem) else {
+                return Ok(response);
+            };
+
+            // The new body is neither the length nor the encoding of the old.
+            let headers = response.headers_mut();
+            headers.remove(header::CONTENT_LENGTH);
+            headers.remove(header::CONTENT_ENCODING);
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(APPLICATION_PROBLEM_JSON),
+            );
+            *response.body_mut() = Body::from(body);
+            response.extensions_mut().insert(problem);
+            Ok(response)
+        })
+    }
+}
+
+fn header_request_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Switches the routers it wraps to problem details. `instance` is set to
+/// the `x-request-id` of the request, as seen by this layer, or else of the
+/// response, e.g. when added outside [`set_request_id`] and
+/// [`propagate_request_id`].
+///
+/// [`set_request_id`]: super::request_id::set_request_id
+/// [`propagate_request_id`]: super::request_id::propagate_request_id
+pub fn problem_details() -> ProblemDetailsLayer {
+    ProblemDetailsLayer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, middleware::compression::compression};
+    use axum::{routing::get, Router};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn replaces_compressed_body() {
+        let router = Router::new()
+            .route("/", get(|| async { Error::NotFound }))
+            .layer(compression())
+            .layer(problem_details());
+        let request = Request::get("/")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .header(X_REQUEST_ID, "42")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.headers().get(header::CONTENT_ENCODING), None);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            APPLICATION_PROBLEM_JSON
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["status"], 404);
+        assert_eq!(problem["instance"], "42");
+    }
+}
@@ -1,5 +1,8 @@
 use super::{DEFAULT_MESSAGE_LEVEL, DIRECT_CONNECT_IP, X_FORWARDED_FOR, X_REAL_IP, X_REQUEST_ID};
-use axum::{extract::Request, http::HeaderName};
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{Extensions, HeaderName},
+};
 use std::net::SocketAddr;
 use tower_http::{
     classify::{ServerErrorsAsFailures, SharedClassifier},
@@ -40,11 +43,7 @@ impl Default for CustomMakeSpan {
 
 impl<B> MakeSpan<B> for CustomMakeSpan {
     fn make_span(&mut self, req: &Request<B>) -> Span {
-        let direct_connect_ip = req
-            .extensions()
-            .get::<axum::extract::connect_info::ConnectInfo<SocketAddr>>()
-            .map(|addr| addr.ip().to_string())
-            .unwrap_or("N/A".to_string());
+        let direct_connect_ip = direct_connect_ip(req.extensions());
         let header_value = |header_name: &'static str| {
             req.headers()
                 .get(HeaderName::from_static(header_name))
@@ -89,6 +88,19 @@ impl<B> MakeSpan<B> for CustomMakeSpan {
     }
 }
 
+/// Peer IP for TCP connections, or the socket path and peer credentials for
+/// Unix domain socket connections.
+fn direct_connect_ip(extensions: &Extensions) -> String {
+    if let Some(ConnectInfo(addr)) = extensions.get::<ConnectInfo<SocketAddr>>() {
+        return addr.ip().to_string();
+    }
+    #[cfg(unix)]
+    if let Some(ConnectInfo(peer)) = extensions.get::<ConnectInfo<crate::general::UnixPeer>>() {
+        return peer.to_string();
+    }
+    "N/A".to_string()
+}
+
 pub fn trace() -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, CustomMakeSpan> {
     TraceLayer::new_for_http().make_span_with(CustomMakeSpan::default())
 }
@@ -1,26 +1,76 @@
+use crate::config::Secret;
 use anyhow::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
-use std::{sync::OnceLock, time::Duration};
+use std::{path::Path, sync::OnceLock, time::Duration};
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[validate(schema(function = "validate_pool_size"))]
 pub struct PostgresConfig {
-    pub url: String,
+    #[cfg_attr(
+        feature = "schema",
+        schemars(example = "postgres://postgres:@127.0.0.1:5432/postgres")
+    )]
+    pub url: Secret<String>,
+    #[serde(default = "default_max_connections")]
+    #[validate(range(min = 1, message = "must be at least 1"))]
     pub max_connections: u32,
+    /// Must not exceed `max_connections`.
+    #[serde(default)]
     pub min_connections: u32,
-    pub acquire_timeout: u64,
-    pub idle_timeout: u64,
-    pub max_lifetime: u64,
+    #[serde(default = "default_acquire_timeout", with = "crate::config::duration")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::config::duration::schema")
+    )]
+    pub acquire_timeout: Duration,
+    #[serde(default = "default_idle_timeout", with = "crate::config::duration")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::config::duration::schema")
+    )]
+    pub idle_timeout: Duration,
+    #[serde(default = "default_max_lifetime", with = "crate::config::duration")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::config::duration::schema")
+    )]
+    pub max_lifetime: Duration,
+}
+
+fn default_max_connections() -> u32 {
+    10
+}
+
+fn default_acquire_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_idle_timeout() -> Duration {
+    Duration::from_secs(10 * 60)
+}
+
+fn default_max_lifetime() -> Duration {
+    Duration::from_secs(30 * 60)
+}
+
+fn validate_pool_size(config: &PostgresConfig) -> Result<(), ValidationError> {
+    if config.min_connections > config.max_connections {
+        return Err(ValidationError::new("min_connections")
+            .with_message("`min_connections` must not exceed `max_connections`".into()));
+    }
+    Ok(())
 }
 
 static PG_POOL: OnceLock<PgPool> = OnceLock::new();
 static PG_TIMEZONE: OnceLock<String> = OnceLock::new();
 
-pub async fn init(config: &PostgresConfig) -> Result<()> {
-    let tz = iana_time_zone::get_timezone().unwrap_or_else(|_| "UTC".to_string());
+/// Creates a new pool without touching the global accessor.
+pub async fn connect(config: &PostgresConfig) -> Result<PgPool> {
     PG_TIMEZONE
-        .set(tz)
-        .map_err(|_| anyhow::anyhow!("Failed to set PG_TIMEZONE"))?;
+        .get_or_init(|| iana_time_zone::get_timezone().unwrap_or_else(|_| "UTC".to_string()));
     let pool = PgPoolOptions::new()
         .after_connect(|conn, _meta| {
             Box::pin(async move {
@@ -31,20 +81,53 @@ pub async fn init(config: &PostgresConfig) -> Result<()> {
         })
         .max_connections(config.max_connections)
         .min_connections(config.min_connections)
-        .acquire_timeout(Duration::from_secs(config.acquire_timeout))
-        .idle_timeout(Some(Duration::from_secs(config.idle_timeout)))
-        .max_lifetime(Some(Duration::from_secs(config.max_lifetime)))
-        .connect(config.url.as_str())
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(Some(config.idle_timeout))
+        .max_lifetime(Some(config.max_lifetime))
+        .connect(config.url.expose())
         .await?;
+    Ok(pool)
+}
+
+/// Applies the migrations found in `source`, e.g. `./migrations`.
+pub async fn migrate(pool: &PgPool, source: &Path) -> Result<()> {
+    sqlx::migrate::Migrator::new(source)
+        .await?
+        .run(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn init(config: &PostgresConfig) -> Result<()> {
+    let pool = connect(config).await?;
     PG_POOL
         .set(pool)
         .map_err(|_| anyhow::anyhow!("Failed to set OnceLock<PgPool>"))
 }
 
+/// Publishes `pool` through [`conn`] unless a pool is already installed.
+/// Returns whether the pool was installed.
+pub(crate) fn install(pool: &PgPool) -> bool {
+    PG_POOL.set(pool.clone()).is_ok()
+}
+
+/// Returns the pool installed by [`init`] or by the first `Application`.
+///
+/// Unlike `redis::conn()`, the pool is kept for the rest of the process:
+/// once that application stops, this returns its closed pool, and pools of
+/// later applications are never installed. Pass the pool through
+/// `AppContext` instead when several applications run in one process.
 pub fn conn() -> &'static PgPool {
     PG_POOL.get().expect("OnceLock<PgPool> not initialized")
 }
 
+/// Closes the pool, waiting for checked-out connections to be returned.
+pub async fn close() {
+    if let Some(pool) = PG_POOL.get() {
+        pool.close().await;
+    }
+}
+
 pub fn pg_session_timezone() -> &'static str {
     PG_TIMEZONE.get().expect("PG_TIMEZONE not initialized")
 }
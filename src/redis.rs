@@ -1,27 +1,61 @@
+use crate::config::Secret;
 use anyhow::{anyhow, Result};
 use redis::Client;
-use serde::Deserialize;
-use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+pub type RedisPool = bb8::Pool<Client>;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RedisConfig {
-    pub url: String,
+    #[cfg_attr(feature = "schema", schemars(example = "redis://127.0.0.1:6379"))]
+    pub url: Secret<String>,
 }
 
-static REDIS_POOL: OnceLock<bb8::Pool<Client>> = OnceLock::new();
+static REDIS_POOL: RwLock<Option<RedisPool>> = RwLock::new(None);
 
-pub async fn init(config: &RedisConfig) -> Result<()> {
-    let client = Client::open(config.url.as_str())?;
+/// Creates a new pool without touching the global accessor.
+pub async fn connect(config: &RedisConfig) -> Result<RedisPool> {
+    let client = Client::open(config.url.expose().as_str())?;
     let pool = bb8::Pool::builder().build(client).await?;
-    REDIS_POOL
-        .set(pool)
-        .map_err(|_| anyhow!("Failed to set OnceLock<RedisPool>"))
+    Ok(pool)
+}
+
+pub async fn init(config: &RedisConfig) -> Result<()> {
+    let pool = connect(config).await?;
+    if !install(&pool) {
+        return Err(anyhow!("Failed to set RwLock<RedisPool>"));
+    }
+    Ok(())
+}
+
+/// Publishes `pool` through [`conn`] unless a pool is already installed.
+/// Returns whether the pool was installed.
+pub(crate) fn install(pool: &RedisPool) -> bool {
+    match REDIS_POOL.write() {
+        Ok(mut guard) if guard.is_none() => {
+            *guard = Some(pool.clone());
+            true
+        }
+        _ => false,
+    }
 }
 
 pub async fn conn() -> Result<bb8::PooledConnection<'static, Client>> {
-    Ok(REDIS_POOL
-        .get()
-        .ok_or_else(|| anyhow!("OnceLock<RedisPool> not initialized"))?
-        .get()
-        .await?)
+    let pool = REDIS_POOL
+        .read()
+        .map_err(|_| anyhow!("RwLock<RedisPool> poisoned"))?
+        .clone()
+        .ok_or_else(|| anyhow!("RwLock<RedisPool> not initialized"))?;
+    Ok(pool.get_owned().await?)
+}
+
+/// Drops the pool; idle connections are closed once the last checked-out
+/// connection is returned.
+pub fn close() {
+    if let Ok(mut guard) = REDIS_POOL.write() {
+        guard.take();
+    }
 }
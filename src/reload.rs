@@ -0,0 +1,221 @@
+//! Reloads the configuration on `SIGHUP` or when a configuration file changes.
+//!
+//! Only the log level, the CORS origins and the `[app]` section are applied
+//! live. A reload that changes anything else is rejected with the list of
+//! differences, and the running configuration is kept.
+//!
+//! There is no rate limiter to reconfigure; settings of one provided by the
+//! application belong in `[app]`, read through `AppContext::config()`.
+
+use crate::{config::Config, logger, shutdown::Shutdown};
+use anyhow::{anyhow, Result};
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ReloadConfig {
+    /// Polls the configuration files for changes, in addition to `SIGHUP`.
+    pub watch: bool,
+    /// Interval between checks for changed configuration files.
+    #[serde(with = "crate::config::duration")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::config::duration::schema")
+    )]
+    pub poll_interval: Duration,
+}
+
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self {
+            watch: true,
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+type Loader<Ext> = Arc<dyn Fn() -> Result<Config<Ext>> + Send + Sync>;
+
+/// Where to read the configuration from again, and which files to watch.
+pub(crate) struct ConfigSource<Ext> {
+    load: Loader<Ext>,
+    files: Vec<PathBuf>,
+}
+
+impl<Ext> ConfigSource<Ext> {
+    pub(crate) fn new<F>(load: F, files: Vec<PathBuf>) -> Self
+    where
+        F: Fn() -> Result<Config<Ext>> + Send + Sync + 'static,
+    {
+        Self {
+            load: Arc::new(load),
+            files,
+        }
+    }
+}
+
+/// Reloads until `shutdown` is triggered.
+pub(crate) async fn run<Ext>(
+    source: ConfigSource<Ext>,
+    current: Arc<ArcSwap<Config<Ext>>>,
+    shutdown: Shutdown,
+) -> Result<()>
+where
+    Ext: Send + Sync + 'static,
+{
+    let (watch, poll_interval) = {
+        let config = current.load();
+        (config.reload.watch, config.reload.poll_interval)
+    };
+    let mut ticker = tokio::time::interval(poll_interval.max(Duration::from_millis(100)));
+    let mut last_modified = modified(&source.files);
+
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+
+    loop {
+        #[cfg(unix)]
+        let hangup = hangup.recv();
+        #[cfg(not(unix))]
+        let hangup = std::future::pending::<Option<()>>();
+
+        let reason = tokio::select! {
+            _ = shutdown.triggered() => return Ok(()),
+            _ = hangup => "SIGHUP",
+            _ = ticker.tick(), if watch => {
+                let now = modified(&source.files);
+                if now == last_modified {
+                    continue;
+                }
+                last_modified = now;
+                "a file change"
+            }
+        };
+
+        tracing::info!("reloading configuration after {}", reason);
+        let load = source.load.clone();
+        let reloaded = tokio::task::spawn_blocking(move || load())
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|config| config)
+            .and_then(|config| apply(&current, config));
+        match reloaded {
+            Ok(()) => tracing::info!("configuration reloaded"),
+            Err(err) => tracing::error!(
+                "configuration reload rejected, keeping the current configuration: {:#}",
+                err
+            ),
+        }
+    }
+}
+
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| std::fs::metadata(file).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+fn apply<Ext>(current: &ArcSwap<Config<Ext>>, config: Config<Ext>) -> Result<()> {
+    let previous = current.load_full();
+    let changes = restart_required(&previous, &config);
+    if !changes.is_empty() {
+        return Err(anyhow!(
+            "changes require a restart:\n  {}",
+            changes.join("\n  ")
+        ));
+    }
+
+    if config.logger.level != previous.logger.level {
+        logger::set_level(config.logger.level)?;
+    }
+    current.store(Arc::new(config));
+    Ok(())
+}
+
+/// Lists the differences in sections that are only read at startup.
+fn restart_required<Ext>(previous: &Config<Ext>, config: &Config<Ext>) -> Vec<String> {
+    let mut changes = Vec::new();
+    diff(
+        "general",
+        &json(&previous.general),
+        &json(&config.general),
+        &mut changes,
+    );
+    diff(
+        "admin",
+        &json(&previous.admin),
+        &json(&config.admin),
+        &mut changes,
+    );
+    diff(
+        "reload",
+        &json(&previous.reload),
+        &json(&config.reload),
+        &mut changes,
+    );
+
+    let logger = |config: &Config<Ext>| {
+        let mut logger = json(&config.logger);
+        if let Value::Object(logger) = &mut logger {
+            logger.remove("level");
+        }
+        logger
+    };
+    diff("logger", &logger(previous), &logger(config), &mut changes);
+
+    #[cfg(feature = "postgres")]
+    {
+        diff(
+            "postgres",
+            &json(&previous.postgres),
+            &json(&config.postgres),
+            &mut changes,
+        );
+        if previous.postgres.url != config.postgres.url {
+            changes.push("postgres.url: changed".to_string());
+        }
+    }
+
+    #[cfg(feature = "redis")]
+    if previous.redis.url != config.redis.url {
+        changes.push("redis.url: changed".to_string());
+    }
+
+    changes
+}
+
+fn json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+fn diff(key: &str, previous: &Value, value: &Value, changes: &mut Vec<String>) {
+    match (previous, value) {
+        (Value::Object(previous), Value::Object(value)) => {
+            let mut keys: Vec<_> = previous.keys().chain(value.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for child in keys {
+                diff(
+                    &format!("{}.{}", key, child),
+                    previous.get(child).unwrap_or(&Value::Null),
+                    value.get(child).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        (previous, value) if previous != value => {
+            changes.push(format!("{}: {} -> {}", key, previous, value));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,61 @@
+use tokio_util::sync::CancellationToken;
+
+/// Cloneable handle used to trigger and observe a graceful shutdown.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a graceful shutdown, as if `SIGTERM` had been received.
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves once a shutdown has been triggered.
+    pub async fn triggered(&self) {
+        self.token.cancelled().await
+    }
+}
+
+/// Resolves on `SIGINT`, `SIGTERM` or a programmatic [`Shutdown::trigger`],
+/// and marks `shutdown` as triggered so every observer is notified.
+pub(crate) async fn signal(shutdown: Shutdown) {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("failed to install SIGINT handler: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("failed to install SIGTERM handler: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT, starting graceful shutdown"),
+        _ = terminate => tracing::info!("received SIGTERM, starting graceful shutdown"),
+        _ = shutdown.triggered() => tracing::info!("shutdown triggered, starting graceful shutdown"),
+    }
+    shutdown.trigger();
+}
@@ -0,0 +1,194 @@
+//! In-process test client for routers built with axum-kit.
+//!
+//! Requests are sent through [`ServiceExt::oneshot`] without binding a socket,
+//! with a fake [`ConnectInfo<SocketAddr>`] attached so that middleware such as
+//! [`CustomMakeSpan`] behaves as it does behind a real listener.
+//!
+//! [`CustomMakeSpan`]: crate::middleware::trace::CustomMakeSpan
+
+use crate::{
+    bootstrap::Application,
+    error::{ErrorResponse, Problem},
+};
+use anyhow::Result;
+use axum::{
+    body::{Body, Bytes},
+    extract::ConnectInfo,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode},
+    Router,
+};
+use http_body_util::BodyExt;
+use serde::{de::DeserializeOwned, Serialize};
+use std::net::{Ipv4Addr, SocketAddr};
+use tower::ServiceExt;
+
+const DEFAULT_REMOTE_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 12345);
+
+#[derive(Debug, Clone)]
+pub struct TestClient {
+    router: Router,
+    remote_addr: SocketAddr,
+}
+
+impl TestClient {
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            remote_addr: DEFAULT_REMOTE_ADDR,
+        }
+    }
+
+    /// Builds the application's router (connecting the pools and running the
+    /// `on_init` hooks) and wraps it, without binding a listener.
+    pub async fn from_application<Ext>(application: Application<Ext>) -> Result<Self>
+    where
+        Ext: Send + Sync + 'static,
+    {
+        Ok(Self::new(application.into_router().await?))
+    }
+
+    /// Sets the peer address exposed through `ConnectInfo<SocketAddr>`.
+    pub fn remote_addr(mut self, remote_addr: SocketAddr) -> Self {
+        self.remote_addr = remote_addr;
+        self
+    }
+
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest {
+        TestRequest {
+            router: self.router.clone(),
+            remote_addr: self.remote_addr,
+            builder: Request::builder().method(method).uri(uri),
+            body: Body::empty(),
+        }
+    }
+
+    pub fn get(&self, uri: &str) -> TestRequest {
+        self.request(Method::GET, uri)
+    }
+
+    pub fn post(&self, uri: &str) -> TestRequest {
+        self.request(Method::POST, uri)
+    }
+
+    pub fn put(&self, uri: &str) -> TestRequest {
+        self.request(Method::PUT, uri)
+    }
+
+    pub fn patch(&self, uri: &str) -> TestRequest {
+        self.request(Method::PATCH, uri)
+    }
+
+    pub fn delete(&self, uri: &str) -> TestRequest {
+        self.request(Method::DELETE, uri)
+    }
+}
+
+#[must_use = "requests do nothing until `send` is awaited"]
+pub struct TestRequest {
+    router: Router,
+    remote_addr: SocketAddr,
+    builder: axum::http::request::Builder,
+    body: Body,
+}
+
+impl TestRequest {
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<axum::http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<axum::http::Error>,
+    {
+        self.builder = self.builder.header(key, value);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Serializes `value` as the request body and sets `Content-Type: application/json`.
+    pub fn json<T: Serialize>(self, value: &T) -> Self {
+        let body = serde_json::to_vec(value).expect("failed to serialize request body");
+        self.header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+    }
+
+    pub async fn send(self) -> TestResponse {
+        let mut request = self
+            .builder
+            .body(self.body)
+            .expect("failed to build request");
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(self.remote_addr));
+        let response = self
+            .router
+            .oneshot(request)
+            .await
+            .unwrap_or_else(|err| match err {});
+        let (parts, body) = response.into_parts();
+        let body = body
+            .collect()
+            .await
+            .expect("failed to read response body")
+            .to_bytes();
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserializes the body, panicking with the raw body if it does not match `T`.
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap_or_else(|err| {
+            panic!(
+                "failed to deserialize response body: {}\nbody: {}",
+                err,
+                self.text()
+            )
+        })
+    }
+
+    /// Deserializes the body as the [`ErrorResponse`] produced by [`crate::error::Error`].
+    pub fn error(&self) -> ErrorResponse {
+        self.json()
+    }
+
+    /// Deserializes the body as the [`Problem`] rendered in problem details mode.
+    pub fn problem(&self) -> Problem {
+        self.json()
+    }
+}
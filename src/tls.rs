@@ -0,0 +1,295 @@
+use crate::general::Accept;
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::ConnectInfo,
+    http::Extensions,
+    serve::{IncomingStream, Listener},
+};
+use rustls::{
+    crypto::CryptoProvider,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier},
+    sign::CertifiedKey,
+    InconsistentKeys, RootCertStore, ServerConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, RwLock, Weak},
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Handshakes in progress at once; further connections wait in the backlog.
+const MAX_PENDING_HANDSHAKES: usize = 1024;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TlsConfig {
+    /// PEM file holding the certificate chain, leaf first.
+    #[cfg_attr(feature = "schema", schemars(example = "./tls/server.pem"))]
+    pub cert: String,
+    /// PEM file holding the private key.
+    #[cfg_attr(feature = "schema", schemars(example = "./tls/server.key"))]
+    pub key: String,
+    /// PEM file holding the CAs trusted to sign client certificates.
+    #[serde(default)]
+    #[cfg_attr(feature = "schema", schemars(example = "./tls/ca.pem"))]
+    pub client_ca: Option<String>,
+    /// Rejects clients that do not present a certificate signed by `client_ca`.
+    #[serde(default)]
+    pub require_client_cert: bool,
+    /// Interval between checks for a changed `cert` or `key` file.
+    #[serde(default = "default_reload_interval", with = "crate::config::duration")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::config::duration::schema")
+    )]
+    pub reload_interval: Duration,
+}
+
+fn default_reload_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// Certificate chain presented and verified during the TLS handshake,
+/// inserted as a request extension when the client sent one.
+#[derive(Debug, Clone)]
+pub struct PeerCertificates(pub Arc<[CertificateDer<'static>]>);
+
+impl PeerCertificates {
+    /// Returns the client's own certificate.
+    pub fn leaf(&self) -> Option<&CertificateDer<'static>> {
+        self.0.first()
+    }
+}
+
+/// Serves the current certificate, reloading it when the files change on disk.
+#[derive(Debug)]
+struct CertResolver {
+    cert_path: String,
+    key_path: String,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<(Arc<CertifiedKey>, Option<SystemTime>)>,
+}
+
+impl CertResolver {
+    fn new(config: &TlsConfig, provider: Arc<CryptoProvider>) -> Result<Self> {
+        let certified_key = load_certified_key(&config.cert, &config.key, &provider)?;
+        Ok(Self {
+            cert_path: config.cert.clone(),
+            key_path: config.key.clone(),
+            provider,
+            current: RwLock::new((Arc::new(certified_key), modified(&config.cert, &config.key))),
+        })
+    }
+
+    fn reload_if_changed(&self) {
+        let modified = modified(&self.cert_path, &self.key_path);
+        let unchanged = self
+            .current
+            .read()
+            .map(|current| current.1 == modified)
+            .unwrap_or(true);
+        if unchanged {
+            return;
+        }
+        match load_certified_key(&self.cert_path, &self.key_path, &self.provider) {
+            Ok(certified_key) => {
+                if let Ok(mut current) = self.current.write() {
+                    *current = (Arc::new(certified_key), modified);
+                    tracing::info!("reloaded TLS certificate from {}", self.cert_path);
+                }
+            }
+            // Keep serving the previous certificate; the files may be mid-rotation.
+            Err(err) => tracing::error!("failed to reload TLS certificate: {:?}", err),
+        }
+    }
+
+    /// Polls the files until the resolver is dropped along with its listener.
+    fn watch(resolver: Weak<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match resolver.upgrade() {
+                    Some(resolver) => resolver.reload_if_changed(),
+                    None => break,
+                }
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.current.read().ok().map(|current| current.0.clone())
+    }
+}
+
+fn modified(cert_path: &str, key_path: &str) -> Option<SystemTime> {
+    let cert = std::fs::metadata(cert_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    let key = std::fs::metadata(key_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    Some(cert.max(key))
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read certificates from {}", path))?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificates found in {}", path));
+    }
+    Ok(certs)
+}
+
+fn load_certified_key(
+    cert_path: &str,
+    key_path: &str,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey> {
+    let certs = load_certs(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("failed to read private key from {}", key_path))?;
+    let signing_key = provider
+        .key_provider
+        .load_private_key(key)
+        .with_context(|| format!("unsupported private key in {}", key_path))?;
+    let certified_key = CertifiedKey::new(certs, signing_key);
+    // Rotations rarely replace both files at once; a mismatched pair would
+    // fail every handshake. Keys that cannot tell their public key pass, as
+    // with `ServerConfig::with_single_cert`.
+    match certified_key.keys_match() {
+        Ok(()) | Err(rustls::Error::InconsistentKeys(InconsistentKeys::Unknown)) => {}
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("{} does not match the key in {}", cert_path, key_path))
+        }
+    }
+    Ok(certified_key)
+}
+
+fn server_config(config: &TlsConfig, resolver: Arc<CertResolver>) -> Result<ServerConfig> {
+    let provider = resolver.provider.clone();
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &config.client_ca {
+        Some(client_ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(client_ca)? {
+                roots.add(cert)?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = if config.require_client_cert {
+                verifier.build()?
+            } else {
+                verifier.allow_unauthenticated().build()?
+            };
+            builder.with_client_cert_verifier(verifier)
+        }
+        None if config.require_client_cert => {
+            return Err(anyhow!("`require_client_cert` needs `client_ca`"));
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = builder.with_cert_resolver(resolver);
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+/// TCP listener that completes TLS handshakes before handing connections
+/// to the server. Handshakes run concurrently so a slow client cannot stall
+/// the accept loop, up to 1024 at a time.
+pub struct TlsListener {
+    inner: TcpListener,
+    acceptor: TlsAcceptor,
+    handshakes: JoinSet<io::Result<(TlsStream<TcpStream>, SocketAddr)>>,
+}
+
+impl TlsListener {
+    pub fn new(inner: TcpListener, config: &TlsConfig) -> Result<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let resolver = Arc::new(CertResolver::new(config, provider)?);
+        CertResolver::watch(
+            Arc::downgrade(&resolver),
+            config.reload_interval.max(Duration::from_secs(1)),
+        );
+        let server_config = server_config(config, resolver)?;
+        Ok(Self {
+            inner,
+            acceptor: TlsAcceptor::from(Arc::new(server_config)),
+            handshakes: JoinSet::new(),
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            tokio::select! {
+                accepted = self.inner.accept(), if self.handshakes.len() < MAX_PENDING_HANDSHAKES => match accepted {
+                    Ok((stream, addr)) => {
+                        let acceptor = self.acceptor.clone();
+                        self.handshakes.spawn(async move {
+                            let stream = tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+                                .await
+                                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out"))??;
+                            Ok((stream, addr))
+                        });
+                    }
+                    Err(err) => handle_accept_error(err).await,
+                },
+                Some(handshake) = self.handshakes.join_next(), if !self.handshakes.is_empty() => {
+                    match handshake {
+                        Ok(Ok(accepted)) => return accepted,
+                        Ok(Err(err)) => tracing::debug!("TLS handshake failed: {}", err),
+                        Err(err) => tracing::error!("TLS handshake task failed: {}", err),
+                    }
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+impl Accept for TlsListener {
+    fn extensions(stream: &IncomingStream<'_, Self>) -> Extensions {
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(*stream.remote_addr()));
+        if let Some(certs) = stream.io().get_ref().1.peer_certificates() {
+            extensions.insert(PeerCertificates(certs.to_vec().into()));
+        }
+        extensions
+    }
+}
+
+async fn handle_accept_error(err: io::Error) {
+    if matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    ) {
+        return;
+    }
+    // Most likely out of file descriptors; back off instead of spinning.
+    tracing::error!("accept error: {}", err);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+}